@@ -0,0 +1,159 @@
+use std::{
+    io::{stdout, Write},
+    thread::sleep,
+    time::Duration,
+};
+
+use crate::simulator::{Simulator, TrainState};
+
+/// Width of the bar showing how far a train has travelled on its edge
+const BAR: usize = 30;
+
+/// Shows the simulation in the terminal frame by frame: the trains on their edges, the trains and
+/// waiting packages at every station and the load of every train
+pub struct Animation {
+    /// Time a frame is shown
+    delay: Duration,
+    /// Whether a frame is shown at every event rather than every unit of time
+    by_event: bool,
+}
+
+impl Animation {
+    pub fn new(delay: Duration, by_event: bool) -> Self {
+        Self { delay, by_event }
+    }
+
+    /// Function to simulate until there is nothing left to simulate, drawing a frame after every step
+    pub fn run(&self, simulator: &mut Simulator) -> Result<(), String> {
+        let mut running = true;
+        loop {
+            // Clear the screen and draw from the top left corner
+            print!("\x1b[2J\x1b[H{}", frame(simulator));
+            stdout().flush().unwrap();
+            if !running {
+                return Ok(());
+            }
+            sleep(self.delay);
+            running = if self.by_event {
+                simulator.run_until_event()?
+            } else {
+                simulator.step()?
+            };
+        }
+    }
+}
+
+/// Function to draw the state of the simulation at the current time
+pub fn frame(simulator: &Simulator) -> String {
+    let network = simulator.get_network();
+    let train = simulator.get_train_state();
+    let mut ret = vec![format!("\x1b[1mTime {}\x1b[0m", simulator.get_time())];
+    ret.push("Lines".to_string());
+    for t in train.iter().filter(|t| t.on_edge) {
+        let edge = network.get_edge(t.location.clone());
+        let edge = edge.lock().unwrap();
+        ret.push(format!(
+            "  {:<8} {} {} {}  {}/{}",
+            t.location,
+            edge.get_start_node().lock().unwrap().get_name(),
+            bar(t),
+            edge.get_end_node().lock().unwrap().get_name(),
+            t.time_on_edge,
+            t.trip_time
+        ));
+    }
+    ret.push("Stations".to_string());
+    for n in network.sorted_node() {
+        let n = n.lock().unwrap();
+        let here = train
+            .iter()
+            .filter(|t| !t.on_edge && t.location == n.get_name())
+            .map(|t| t.name.clone())
+            .collect::<Vec<String>>();
+        let waiting = n
+            .get_package()
+            .iter()
+            .map(|p| {
+                let p = p.lock().unwrap();
+                format!("{}({})", p.get_name(), p.get_size())
+            })
+            .collect::<Vec<String>>();
+        ret.push(format!(
+            "  {:<8} trains [{}]  waiting [{}]",
+            n.get_name(),
+            here.join(", "),
+            waiting.join(", ")
+        ));
+    }
+    ret.push("Trains".to_string());
+    for t in train.iter() {
+        let load = match t.load.to_string() {
+            l if l.is_empty() => "0".to_string(),
+            l => l,
+        };
+        ret.push(format!(
+            "  {:<8} {} {}, load {} of {} [{}]{}{}",
+            t.name,
+            if t.on_edge { "on" } else { "at" },
+            t.location,
+            load,
+            t.capacity,
+            t.package.join(", "),
+            if t.route.is_empty() {
+                "".to_string()
+            } else {
+                format!(", route {}", t.route.join(" -> "))
+            },
+            if t.end_trip { ", ended" } else { "" }
+        ));
+    }
+    ret.join("\n") + "\n"
+}
+
+/// Function to draw how far a train has travelled on its edge, e.g. `[=====Q1>.........]`
+fn bar(t: &TrainState) -> String {
+    let done = (t.time_on_edge as usize * BAR)
+        .checked_div(t.trip_time as usize)
+        .unwrap_or(BAR)
+        .min(BAR);
+    let label = format!("{}>", t.name);
+    let before = done.saturating_sub(label.len());
+    let after = BAR.saturating_sub(before + label.len());
+    format!("[{}{}{}]", "=".repeat(before), label, ".".repeat(after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+
+    #[test]
+    fn frame_shows_the_trains_stations_and_loads() {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let network = create_network(
+            lines(&["A", "B", "C"]),
+            lines(&["E1,A,B,10", "E2,B,C,5"]),
+            lines(&["Q1,6,A", "Q2,6,C"]),
+            lines(&["K1,5,A,C", "K2,1,B,C"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        let mut simulator = Simulator::new(network);
+        simulator.run_until(4).unwrap();
+        assert_eq!(
+            frame(&simulator).lines().collect::<Vec<&str>>(),
+            vec![
+                "\x1b[1mTime 4\x1b[0m",
+                "Lines",
+                "  E1       A [=========Q1>..................] B  4/10",
+                "  E2 R     C [=====================Q2>......] B  4/5",
+                "Stations",
+                "  A        trains []  waiting []",
+                "  B        trains []  waiting [K2(weight:1)]",
+                "  C        trains []  waiting []",
+                "Trains",
+                "  Q1       on E1, load weight:5 of weight:6 [K1], route B",
+                "  Q2       on E2 R, load 0 of weight:6 [], route B",
+            ]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::json::Json;
+
+/// The indicators of every train which are compared
+const TRAIN_KPI: [&str; 11] = [
+    "distance",
+    "empty_distance",
+    "travel_time",
+    "idle_time",
+    "queue_time",
+    "wait_time",
+    "delay_time",
+    "rest_time",
+    "stops",
+    "mean_utilisation",
+    "max_utilisation",
+];
+
+/// The indicators of every package which are compared
+const PACKAGE_KPI: [&str; 3] = ["delivered_at", "wait_time", "transit_time"];
+
+/// Function to compare the results of two simulations, as written by `Network::to_json`
+/// Reports the trains which carried every package, the routes of the trains and the KPIs which differ,
+/// or that there is no difference
+pub fn diff(a: &Json, b: &Json) -> Vec<String> {
+    let mut ret = vec![];
+    if let Some(line) = compare(
+        "Makespan",
+        "",
+        kpi(a).get("makespan"),
+        kpi(b).get("makespan"),
+    ) {
+        ret.push(line);
+    }
+    let (carrier_a, carrier_b) = (carrier(a), carrier(b));
+    for p in union(&carrier_a, &carrier_b) {
+        let (x, y) = (carrier_a.get(&p), carrier_b.get(&p));
+        if x != y {
+            ret.push(format!(
+                "Package {}: carried by {} -> {}",
+                p,
+                x.map_or("nobody".to_string(), |t| t.join(", ")),
+                y.map_or("nobody".to_string(), |t| t.join(", "))
+            ));
+        }
+    }
+    let (package_a, package_b) = (
+        by_name(kpi(a).get("packages")),
+        by_name(kpi(b).get("packages")),
+    );
+    for p in union(&package_a, &package_b) {
+        for k in PACKAGE_KPI {
+            let x = package_a.get(&p).and_then(|x| x.get(k));
+            let y = package_b.get(&p).and_then(|x| x.get(k));
+            if let Some(line) = compare(&format!("Package {}", p), k, x, y) {
+                ret.push(line);
+            }
+        }
+    }
+    let (route_a, route_b) = (route(a), route(b));
+    for t in union(&route_a, &route_b) {
+        let (x, y) = (route_a.get(&t), route_b.get(&t));
+        if x != y {
+            ret.push(format!(
+                "Train {}: route {} -> {}",
+                t,
+                x.map_or("-".to_string(), |r| r.join(" > ")),
+                y.map_or("-".to_string(), |r| r.join(" > "))
+            ));
+        }
+    }
+    let (train_a, train_b) = (by_name(kpi(a).get("trains")), by_name(kpi(b).get("trains")));
+    for t in union(&train_a, &train_b) {
+        for k in TRAIN_KPI {
+            let x = train_a.get(&t).and_then(|x| x.get(k));
+            let y = train_b.get(&t).and_then(|x| x.get(k));
+            if let Some(line) = compare(&format!("Train {}", t), k, x, y) {
+                ret.push(line);
+            }
+        }
+    }
+    if ret.is_empty() {
+        ret.push("No differences".to_string());
+    }
+    ret
+}
+
+/// Function to describe the change of a value, if it changed, e.g. `Train Q1: distance 40 -> 50 (+10)`
+fn compare(subject: &str, key: &str, a: Option<&Json>, b: Option<&Json>) -> Option<String> {
+    let (x, y) = (a.and_then(|x| x.as_f64()), b.and_then(|x| x.as_f64()));
+    if x == y {
+        return None;
+    }
+    let show = |v: Option<f64>| match v {
+        None => "-".to_string(),
+        Some(v) if key.ends_with("utilisation") => format!("{:.1}%", v * 100.0),
+        Some(v) => v.to_string(),
+    };
+    let change = match (x, y) {
+        (Some(x), Some(y)) if key.ends_with("utilisation") => {
+            format!(" ({:+.1}%)", (y - x) * 100.0)
+        }
+        (Some(x), Some(y)) => format!(" ({:+})", y - x),
+        _ => "".to_string(),
+    };
+    let key = key.replace('_', " ");
+    Some(format!(
+        "{}:{}{} {} -> {}{}",
+        subject,
+        if key.is_empty() { "" } else { " " },
+        key,
+        show(x),
+        show(y),
+        change
+    ))
+}
+
+fn kpi(result: &Json) -> &Json {
+    result.get("kpis").unwrap_or(&Json::Null)
+}
+
+fn history(result: &Json) -> &[Json] {
+    result
+        .get("history")
+        .and_then(|h| h.as_array())
+        .map_or(&[], |h| h.as_slice())
+}
+
+/// Function to index a list of objects by their name
+fn by_name(list: Option<&Json>) -> BTreeMap<String, &Json> {
+    list.and_then(|l| l.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|x| Some((x.get("name")?.as_str()?.to_string(), x)))
+        .collect()
+}
+
+/// Function to find the trains which picked up every package, the parts of a divisible package count
+/// for the package
+fn carrier(result: &Json) -> BTreeMap<String, Vec<String>> {
+    let mut ret: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for h in history(result) {
+        let train = h.get("t").and_then(|t| t.as_str()).unwrap_or_default();
+        for p in strings(h.get("p1")) {
+            let name = p.split('/').next().unwrap().to_string();
+            ret.entry(name).or_default().insert(train.to_string());
+        }
+    }
+    ret.into_iter()
+        .map(|(p, t)| (p, t.into_iter().collect()))
+        .collect()
+}
+
+/// Function to find the stations every train passed, in order
+fn route(result: &Json) -> BTreeMap<String, Vec<String>> {
+    let mut ret: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for h in history(result) {
+        let train = h.get("t").and_then(|t| t.as_str()).unwrap_or_default();
+        let route = ret.entry(train.to_string()).or_default();
+        if route.is_empty() {
+            route.push(strings(h.get("n1")).concat());
+        }
+        route.push(strings(h.get("n2")).concat());
+    }
+    ret
+}
+
+/// Function to read a string or a list of strings
+fn strings(value: Option<&Json>) -> Vec<String> {
+    match value {
+        Some(Json::String(s)) => vec![s.clone()],
+        Some(Json::Array(a)) => a
+            .iter()
+            .filter_map(|x| x.as_str().map(|x| x.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Function to get the keys of both maps, ordered
+fn union<T>(a: &BTreeMap<String, T>, b: &BTreeMap<String, T>) -> BTreeSet<String> {
+    a.keys().chain(b.keys()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_network, network::Network};
+
+    fn result(train: &[&str]) -> Json {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let mut network: Network = create_network(
+            lines(&["A", "B", "C"]),
+            lines(&["E1,A,B,5", "E2,B,C,3"]),
+            lines(train),
+            lines(&["K1,5,B,C"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        network.simulate().unwrap();
+        network.to_json()
+    }
+
+    #[test]
+    fn same_result_has_no_differences() {
+        let a = result(&["Q1,10,A"]);
+        assert_eq!(diff(&a, &a), vec!["No differences"]);
+    }
+
+    #[test]
+    fn changed_carrier_route_and_kpis_are_reported() {
+        let a = result(&["Q1,10,A"]);
+        let b = result(&["Q1,10,A", "Q2,10,C"]);
+        let diff = diff(&a, &b);
+        assert_eq!(
+            diff[..7],
+            [
+                "Makespan: 8 -> 6 (-2)",
+                "Package K1: carried by Q1 -> Q2",
+                "Package K1: delivered at 8 -> 6 (-2)",
+                "Package K1: wait time 5 -> 3 (-2)",
+                "Train Q1: route A > B > C -> A > B",
+                "Train Q2: route - -> C > B > C",
+                "Train Q1: distance 8 -> 5 (-3)",
+            ]
+        );
+        assert!(diff.contains(&"Train Q1: mean utilisation 18.8% -> 0.0% (-18.8%)".to_string()));
+        assert!(diff.contains(&"Train Q2: stops - -> 2".to_string()));
+    }
+}
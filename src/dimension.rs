@@ -0,0 +1,158 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    num::ParseIntError,
+    str::FromStr,
+};
+
+/// The dimension used when a size or capacity is given as a plain number
+pub const DEFAULT_DIMENSION: &str = "weight";
+
+/// A set of named measurements such as weight, volume and container slots
+/// Used both for the size of a package and for the capacity and load of a train
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Dimensions {
+    value: BTreeMap<String, u64>,
+}
+
+impl Debug for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.value.iter()).finish()
+    }
+}
+
+impl Display for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            &self
+                .value
+                .iter()
+                .map(|(k, v)| format!("{}:{}", k, v))
+                .collect::<Vec<String>>()
+                .join(";"),
+        )
+    }
+}
+
+impl FromStr for Dimensions {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::new();
+        for d in s.split(';').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            match d.split_once(':') {
+                Some((name, amount)) => ret.set(name.trim().to_string(), amount.trim().parse()?),
+                None => ret.set(DEFAULT_DIMENSION.to_string(), d.parse()?),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Dimensions {
+    pub fn new() -> Self {
+        Self {
+            value: BTreeMap::new(),
+        }
+    }
+
+    /// Function to parse the input syntax of a size or capacity
+    /// A plain number such as `6` is a weight, otherwise named dimensions are
+    /// separated by `;`, e.g. `weight:6;volume:10;slots:2`
+    pub fn parse(s: &str) -> Self {
+        s.parse().unwrap()
+    }
+
+    pub fn get(&self, name: &str) -> u64 {
+        *self.value.get(name).unwrap_or(&0)
+    }
+
+    pub fn set(&mut self, name: String, amount: u64) {
+        self.value.insert(name, amount);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.value.keys().cloned().collect()
+    }
+
+    /// Function to check whether `extra` can be added on top of this load without exceeding `capacity`
+    /// A dimension which is not part of the capacity is not limited
+    pub fn fits(&self, extra: &Dimensions, capacity: &Dimensions) -> bool {
+        capacity.value.iter().all(|(name, limit)| {
+            self.get(name)
+                .checked_add(extra.get(name))
+                .is_some_and(|x| x <= *limit)
+        })
+    }
+
+    pub fn add(&mut self, other: &Dimensions) {
+        for (name, amount) in &other.value {
+            let value = self.value.entry(name.clone()).or_insert(0);
+            *value = value.saturating_add(*amount);
+        }
+    }
+
+    pub fn sub(&mut self, other: &Dimensions) {
+        for (name, amount) in &other.value {
+            let value = self.value.entry(name.clone()).or_insert(0);
+            *value = value.saturating_sub(*amount);
+        }
+    }
+
+    /// Function to get the largest share of a capacity used by this load, e.g. 0.5 for half full
+    pub fn utilisation(&self, capacity: &Dimensions) -> f64 {
+        capacity
+            .value
+            .iter()
+            .filter(|(_, limit)| **limit > 0)
+            .map(|(name, limit)| self.get(name) as f64 / *limit as f64)
+            .fold(0.0, f64::max)
+    }
+
+    /// Function to get the share `part / whole` of every dimension, rounded up
+    /// Calculated on 128 bits so that large amounts do not overflow
+    pub fn scale(&self, part: u64, whole: u64) -> Dimensions {
+        let mut ret = Self::new();
+        for (name, amount) in &self.value {
+            let share = (*amount as u128 * part as u128).div_ceil(whole as u128);
+            ret.set(name.clone(), u64::try_from(share).unwrap_or(u64::MAX));
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_number_is_a_weight() {
+        let size = Dimensions::parse("6");
+        assert_eq!(size.get(DEFAULT_DIMENSION), 6);
+        assert_eq!(size, Dimensions::parse("weight:6"));
+        let size = Dimensions::parse("weight:6;volume:10");
+        assert_eq!(size.to_string(), "volume:10;weight:6");
+    }
+
+    #[test]
+    fn fits_only_the_dimensions_of_the_capacity() {
+        let capacity = Dimensions::parse("weight:10;slots:2");
+        let load = Dimensions::parse("weight:6;slots:1");
+        assert!(load.fits(&Dimensions::parse("weight:4;slots:1;volume:100"), &capacity));
+        assert!(!load.fits(&Dimensions::parse("weight:5"), &capacity));
+        assert!(!load.fits(&Dimensions::parse("slots:2"), &capacity));
+    }
+
+    #[test]
+    fn large_amounts_do_not_overflow() {
+        let max = u64::MAX;
+        let capacity = Dimensions::parse(&max.to_string());
+        let load = Dimensions::parse(&max.to_string());
+        assert!(!load.fits(&Dimensions::parse("1"), &capacity));
+        assert_eq!(load.scale(max - 1, max).get(DEFAULT_DIMENSION), max - 1);
+        assert_eq!(load.scale(1, 2).get(DEFAULT_DIMENSION), max / 2 + 1);
+        let mut total = load.clone();
+        total.add(&load);
+        assert_eq!(total.get(DEFAULT_DIMENSION), max);
+    }
+}
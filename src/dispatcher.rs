@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::{
+    network::Network, package::Package, parse::check_package, simulator::Simulator, train::History,
+};
+
+/// Runs the simulation as a continuous dispatcher fed with timestamped events, e.g. orders coming in
+/// during the day, and prints every history record as soon as it is committed
+///
+/// An event is a line starting with its time:
+/// - `15,package,K5,5,A,C` orders a new package, in the same format as the input
+/// - `20,train,Q1,unavailable` stops a train from taking new work
+/// - `40,train,Q1,available` lets it take new work again
+///
+/// A line which is not a valid event is reported and skipped
+pub struct Dispatcher {
+    simulator: Simulator,
+    /// Time between two replans of the work which has not started, never if 0
+    replan_interval: u64,
+    /// Number of history records printed for every train
+    printed: HashMap<String, usize>,
+}
+
+impl Dispatcher {
+    pub fn new(simulator: Simulator, replan_interval: u64) -> Self {
+        Self {
+            simulator,
+            replan_interval,
+            printed: HashMap::new(),
+        }
+    }
+
+    pub fn get_network(&self) -> &Network {
+        self.simulator.get_network()
+    }
+
+    /// Function to simulate while reading the events, `create_package` parses an ordered package
+    /// The simulation ends once the events run out and there is nothing left to simulate
+    pub fn run(
+        &mut self,
+        events: impl Iterator<Item = String>,
+        create_package: impl Fn(&str, &Network) -> Package,
+    ) -> Result<(), String> {
+        for line in events.filter(|e| !e.trim().is_empty()) {
+            let time = match line.split_once(',').map(|(t, _)| t.trim().parse::<u64>()) {
+                Some(Ok(t)) => t,
+                _ => {
+                    println!("Skipped event {}: expected the time first", line);
+                    continue;
+                }
+            };
+            self.run_until(time)?;
+            let field = line
+                .split(',')
+                .skip(1)
+                .map(|x| x.trim())
+                .collect::<Vec<&str>>();
+            if let Err(e) = self.apply(&field, &create_package) {
+                println!("Skipped event {}: {}", line, e);
+                continue;
+            }
+            // New work and changed trains are a reason to plan again
+            self.simulator.get_network_mut().replan();
+        }
+        while self.step()? {}
+        self.print_committed();
+        Ok(())
+    }
+
+    /// Function to apply an event to the network, the fields follow the time
+    /// Returns why the event is not valid without changing anything
+    fn apply(
+        &mut self,
+        field: &[&str],
+        create_package: &impl Fn(&str, &Network) -> Package,
+    ) -> Result<(), String> {
+        match field[0] {
+            "package" => {
+                let line = field[1..].join(",");
+                let network = self.get_network();
+                check_package(&line, |s| network.has_node(s))?;
+                if network.has_package(field[1]) {
+                    return Err(format!("package {} already exists", field[1]));
+                }
+                let package = create_package(&line, network);
+                self.simulator.get_network_mut().add_package(package);
+            }
+            "train" => {
+                let name = field.get(1).copied().unwrap_or_default();
+                if !self.get_network().has_train(name) {
+                    return Err(format!("unknown train {}", name));
+                }
+                let available = match field.get(2).copied() {
+                    Some("available") => true,
+                    Some("unavailable") => false,
+                    _ => return Err("expected available or unavailable".to_string()),
+                };
+                self.simulator
+                    .get_network_mut()
+                    .set_train_available(name.to_string(), available);
+            }
+            other => return Err(format!("unknown event {}", other)),
+        }
+        Ok(())
+    }
+
+    /// Function to simulate until the clock reaches this time
+    /// The clock skips ahead once the trains have nothing to do until the next event
+    fn run_until(&mut self, time: u64) -> Result<(), String> {
+        while self.simulator.get_time() < time {
+            if self.get_network().is_idle() || !self.step()? {
+                self.simulator.get_network_mut().wait_until(time);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Function to simulate 1 unit of time, replanning every `replan_interval`
+    fn step(&mut self) -> Result<bool, String> {
+        let running = self.simulator.step();
+        self.print_committed();
+        let time = self.simulator.get_time();
+        if self.replan_interval > 0 && time.is_multiple_of(self.replan_interval) {
+            self.simulator.get_network_mut().replan();
+        }
+        running
+    }
+
+    /// Function to print the history records which are complete, i.e. the train has arrived at n2
+    fn print_committed(&mut self) {
+        let mut committed: Vec<History> = vec![];
+        for t in self.get_network().sorted_train() {
+            let t = t.lock().unwrap();
+            let printed = self.printed.entry(t.get_name()).or_insert(0);
+            for h in t.get_history().into_iter().skip(*printed) {
+                if !h.has_arrived() {
+                    break;
+                }
+                committed.push(h);
+                *printed += 1;
+            }
+        }
+        committed.sort();
+        for h in committed {
+            println!("{}", h);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_network, create_package};
+
+    #[test]
+    fn ordered_package_is_delivered_and_invalid_events_are_skipped() {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let network = create_network(
+            lines(&["A", "B", "C"]),
+            lines(&["E1,A,B,5", "E2,B,C,5"]),
+            lines(&["Q1,6,A"]),
+            lines(&["K1,5,A,B"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        let mut dispatcher = Dispatcher::new(Simulator::new(network), 0);
+        let events = [
+            "2,package,K2,5,C,A",
+            "bad line",
+            "3,train,Q9,available",
+            "4,package,K3,5,A,Z",
+            "20,train,Q1,unavailable",
+            "30,package,K4,5,A,B",
+            "40,train,Q1,available",
+        ];
+        dispatcher
+            .run(events.iter().map(|x| x.to_string()), create_package)
+            .unwrap();
+        let history = dispatcher
+            .get_network()
+            .get_history()
+            .iter()
+            .map(|h| h.to_string())
+            .collect::<Vec<String>>();
+        // Q1 only takes K4 once it is available again
+        assert_eq!(
+            history,
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]",
+                "W=5, T=Q1, N1=B, P1=[], N2=C, P2=[]",
+                "W=10, T=Q1, N1=C, P1=[\"K2\"], N2=B, P2=[]",
+                "W=15, T=Q1, N1=B, P1=[], N2=A, P2=[\"K2\"]",
+                "W=40, T=Q1, N1=A, P1=[\"K4\"], N2=B, P2=[\"K4\"]",
+            ]
+        );
+        assert!(!dispatcher.get_network().has_package("K3"));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::network::Network;
+
+/// Function to describe the network as a Graphviz DOT graph, e.g. to be drawn with `dot -Tsvg`
+/// Every line is drawn once with its name and journey time and critical nodes are filled
+/// If `route` is set, every trip of the trains in the history is drawn as an arrow in the colour
+/// of the train, labelled with the train and the departure and arrival times
+pub fn to_dot(network: &Network, route: bool) -> String {
+    let mut ret = vec![
+        "graph network {".to_string(),
+        "    node [shape=circle];".to_string(),
+    ];
+    for n in network.sorted_node() {
+        let n = n.lock().unwrap();
+        let mut attribute = vec![];
+        if n.is_critical() {
+            attribute.push("style=filled, fillcolor=gold".to_string());
+        }
+        if n.is_hub() {
+            attribute.push("shape=doublecircle".to_string());
+        }
+        if n.get_refuel_time().is_some() {
+            attribute.push("peripheries=2".to_string());
+        }
+        ret.push(format!("    {}{};", quote(&n.get_name()), list(&attribute)));
+    }
+    let edge = network.sorted_edge();
+    for (i, e) in edge.iter().enumerate() {
+        let e = e.lock().unwrap();
+        // Both directions of a line share a track and are drawn once
+        if edge[..i]
+            .iter()
+            .any(|d| d.lock().unwrap().is_same_track(&e))
+        {
+            continue;
+        }
+        ret.push(format!(
+            "    {} -- {} [label={}];",
+            quote(&e.get_start_node().lock().unwrap().get_name()),
+            quote(&e.get_end_node().lock().unwrap().get_name()),
+            quote(&format!("{} ({})", e.get_name(), e.get_journey_time()))
+        ));
+    }
+    if route {
+        for (i, t) in network.sorted_train().into_iter().enumerate() {
+            let t = t.lock().unwrap();
+            let colour = colour(i);
+            for h in t.get_history().iter().filter(|h| h.has_arrived()) {
+                ret.push(format!(
+                    "    {} -- {} [dir=forward, color={}, fontcolor={}, penwidth=2, label={}];",
+                    quote(&h.get_from()),
+                    quote(&h.get_to()),
+                    colour,
+                    colour,
+                    quote(&format!(
+                        "{} {}-{}",
+                        t.get_name(),
+                        h.get_departure(),
+                        h.get_arrival()
+                    ))
+                ));
+            }
+        }
+    }
+    ret.push("}".to_string());
+    ret.join("\n") + "\n"
+}
+
+/// Function to choose the colour of the routes of the i-th train as hue, saturation and value
+/// The hues are spread by the golden ratio so that every train gets a different colour
+fn colour(i: usize) -> String {
+    let hue = (i as f64 * 0.618_033_988_75).fract();
+    quote(&format!("{:.4} 0.850 0.750", hue))
+}
+
+/// Function to quote a name as a DOT identifier
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Function to write the attributes of a node, if any
+fn list(attribute: &[String]) -> String {
+    if attribute.is_empty() {
+        return "".to_string();
+    }
+    format!(" [{}]", attribute.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::create_network;
+
+    #[test]
+    fn every_train_has_a_colour_of_its_own() {
+        let colours = (0..100).map(colour).collect::<HashSet<String>>();
+        assert_eq!(colours.len(), 100);
+    }
+
+    #[test]
+    fn trips_are_drawn_in_the_colour_of_the_train() {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let mut network = create_network(
+            lines(&["A", "B"]),
+            lines(&["E1,A,B,5"]),
+            lines(&["Q1,6,A"]),
+            lines(&["K1,5,A,B"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        network.simulate().unwrap();
+        assert_eq!(
+            to_dot(&network, true),
+            [
+                "graph network {",
+                "    node [shape=circle];",
+                "    \"A\" [style=filled, fillcolor=gold];",
+                "    \"B\" [style=filled, fillcolor=gold];",
+                "    \"A\" -- \"B\" [label=\"E1 (5)\"];",
+                "    \"A\" -- \"B\" [dir=forward, color=\"0.0000 0.850 0.750\", fontcolor=\"0.0000 0.850 0.750\", penwidth=2, label=\"Q1 0-5\"];",
+                "}\n",
+            ]
+            .join("\n")
+        );
+    }
+}
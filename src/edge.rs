@@ -1,9 +1,15 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::Debug,
     sync::{Arc, Mutex},
 };
 
-use crate::node::Node;
+use crate::{
+    node::Node,
+    parse::{parse_field, parse_optional_field},
+    random::Random,
+    train::TrainClass,
+};
 
 /// The edge structure which connects 2 nodes
 #[derive(Clone)]
@@ -12,6 +18,33 @@ pub struct Edge {
     node1: Arc<Mutex<Node>>,
     node2: Arc<Mutex<Node>>,
     journey_time: i64,
+    /// Journey time before any of the changes in `retime`
+    initial_journey_time: i64,
+    /// The line this edge runs on, shared with the edge of the opposite direction
+    track: Arc<Mutex<Track>>,
+    /// Classes of train which are not allowed on this edge
+    banned: HashSet<String>,
+    /// Times at which the journey time of this edge changes, with the new journey time
+    retime: Vec<(u64, i64)>,
+    /// Range of the random delay added to the journey time of every trip, if the journey time varies
+    random_delay: Option<(u64, u64)>,
+    /// The random number generator used to draw the delays, no delay is drawn without it
+    random: Option<Arc<Mutex<Random>>>,
+}
+
+/// The physical line between 2 nodes which limits how many trains can be on it at once
+#[derive(Clone, Debug, Default)]
+pub struct Track {
+    /// Maximum number of trains on the line in both directions, unlimited if not set
+    capacity: Option<u64>,
+    /// Names of the trains currently on the line
+    train: HashSet<String>,
+    /// Periods in which the line is closed, from the first time until the second time
+    closure: Vec<(u64, u64)>,
+    /// Time at which the current closure of the line ends, if it is closed
+    closed_until: Option<u64>,
+    /// Remaining time of the closure when the line was last updated, used to plan paths
+    delay: u64,
 }
 
 impl Debug for Edge {
@@ -32,9 +65,36 @@ impl Edge {
             node1: n1,
             node2: n2,
             journey_time: jt,
+            initial_journey_time: jt,
+            track: Arc::new(Mutex::new(Track::default())),
+            banned: HashSet::new(),
+            retime: vec![],
+            random_delay: None,
+            random: None,
+        }
+    }
+
+    /// Function to create the edge of the opposite direction running on the same track
+    pub fn reverse(&self, name: String) -> Self {
+        Self {
+            name,
+            node1: self.node2.clone(),
+            node2: self.node1.clone(),
+            journey_time: self.journey_time,
+            initial_journey_time: self.initial_journey_time,
+            track: self.track.clone(),
+            banned: self.banned.clone(),
+            retime: self.retime.clone(),
+            random_delay: self.random_delay,
+            random: self.random.clone(),
         }
     }
 
+    /// Function to check whether both edges run on the same track, i.e. are the two directions of a line
+    pub fn is_same_track(&self, other: &Edge) -> bool {
+        Arc::ptr_eq(&self.track, &other.track)
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -50,4 +110,158 @@ impl Edge {
     pub fn get_journey_time(&self) -> i64 {
         self.journey_time
     }
+
+    /// Function to get the journey time of a trip which starts at this time, after the changes scheduled
+    /// up to then
+    pub fn get_journey_time_at(&self, time: u64) -> i64 {
+        self.retime
+            .iter()
+            .filter(|(t, _)| *t <= time)
+            .max_by_key(|(t, _)| *t)
+            .map_or(self.initial_journey_time, |(_, j)| *j)
+    }
+
+    /// Function to calculate the time needed by a train of this class to travel on this edge
+    /// A faster train divides the journey time by its speed, rounded up to whole units of time
+    pub fn get_travel_time(&self, class: &TrainClass) -> u64 {
+        (self.journey_time as f64 / class.speed).ceil() as u64
+    }
+
+    pub fn set_banned(&mut self, banned: HashSet<String>) {
+        self.banned = banned;
+    }
+
+    /// Function to check whether a train of this class is allowed on this edge
+    pub fn allows(&self, class: &TrainClass) -> bool {
+        !self.banned.contains(&class.name)
+    }
+
+    pub fn set_capacity(&mut self, capacity: Option<u64>) {
+        self.track.lock().unwrap().capacity = capacity;
+    }
+
+    pub fn get_capacity(&self) -> Option<u64> {
+        self.track.lock().unwrap().capacity
+    }
+
+    /// Function to check whether one more train can enter the track
+    pub fn has_room(&self) -> bool {
+        let track = self.track.lock().unwrap();
+        match track.capacity {
+            Some(c) => (track.train.len() as u64) < c,
+            None => true,
+        }
+    }
+
+    pub fn set_closure(&mut self, closure: Vec<(u64, u64)>) {
+        self.track.lock().unwrap().closure = closure;
+    }
+
+    pub fn set_retime(&mut self, retime: Vec<(u64, i64)>) {
+        self.retime = retime;
+    }
+
+    pub fn set_random_delay(&mut self, random_delay: Option<(u64, u64)>) {
+        self.random_delay = random_delay;
+    }
+
+    pub fn set_random(&mut self, random: Arc<Mutex<Random>>) {
+        self.random = Some(random);
+    }
+
+    /// Function to draw the random delay of a trip on this edge
+    /// The delay is 0 unless the journey time varies and a random number generator is set
+    pub fn draw_delay(&self) -> u64 {
+        match (self.random_delay, &self.random) {
+            (Some((low, high)), Some(random)) => random.lock().unwrap().range(low, high),
+            _ => 0,
+        }
+    }
+
+    /// Function to check whether the line closes or opens, or the journey time changes at this time
+    pub fn has_event(&self, time: u64) -> bool {
+        let track = self.track.lock().unwrap();
+        self.retime.iter().any(|(t, _)| *t == time)
+            || track
+                .closure
+                .iter()
+                .any(|(from, until)| *from == time || *until == time)
+    }
+
+    /// Function to find the first time from this time on the line closes or opens, or the journey time changes
+    pub fn next_event(&self, time: u64) -> Option<u64> {
+        let track = self.track.lock().unwrap();
+        self.retime
+            .iter()
+            .map(|(t, _)| *t)
+            .chain(
+                track
+                    .closure
+                    .iter()
+                    .flat_map(|(from, until)| [*from, *until]),
+            )
+            .filter(|t| *t >= time)
+            .min()
+    }
+
+    /// Function to apply the closures and journey time changes scheduled up to this time
+    pub fn update(&mut self, time: u64) {
+        for (t, journey_time) in self.retime.iter() {
+            if *t == time {
+                self.journey_time = *journey_time;
+            }
+        }
+        let mut track = self.track.lock().unwrap();
+        track.closed_until = track
+            .closure
+            .iter()
+            .filter(|(from, until)| *from <= time && time < *until)
+            .map(|(_, until)| *until)
+            .max();
+        track.delay = track.closed_until.map_or(0, |until| until - time);
+    }
+
+    /// Function to describe the state of the edge during the simulation as fields of a snapshot
+    pub fn save_state(&self) -> String {
+        let track = self.track.lock().unwrap();
+        format!(
+            "journey={},closed_until={},delay={}",
+            self.journey_time,
+            track.closed_until.map_or("".to_string(), |x| x.to_string()),
+            track.delay
+        )
+    }
+
+    /// Function to continue from the state saved by `save_state`
+    /// Returns the reason the state is not valid, naming the field
+    pub fn restore_state(&mut self, options: &HashMap<String, String>) -> Result<(), String> {
+        self.journey_time = parse_field(options, "journey")?;
+        let mut track = self.track.lock().unwrap();
+        track.closed_until = parse_optional_field(options, "closed_until")?;
+        track.delay = parse_field(options, "delay")?;
+        Ok(())
+    }
+
+    /// Function to check whether the line is closed, a train cannot enter it until it opens
+    pub fn is_closed(&self) -> bool {
+        self.track.lock().unwrap().closed_until.is_some()
+    }
+
+    /// Function to get the time a train has to wait for the line to open, as of the last update
+    pub fn get_delay(&self) -> u64 {
+        self.track.lock().unwrap().delay
+    }
+
+    pub fn enter(&self, train: String) {
+        self.track.lock().unwrap().train.insert(train);
+    }
+
+    pub fn leave(&self, train: &str) {
+        self.track.lock().unwrap().train.remove(train);
+    }
+
+    /// Function to get the names of the trains on the line in both directions
+    pub fn get_train(&self) -> Vec<String> {
+        self.track.lock().unwrap().train.iter().cloned().collect()
+    }
 }
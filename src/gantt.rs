@@ -0,0 +1,184 @@
+use crate::network::Network;
+
+/// Width of the column of train names
+const LEFT: f64 = 80.0;
+/// Width of the time axis
+const WIDTH: f64 = 1000.0;
+/// Height of the row of every train
+const ROW: f64 = 40.0;
+/// Height of the time axis at the top
+const TOP: f64 = 30.0;
+/// Colours of the bars of the trains, used in turn
+const COLOUR: [&str; 8] = [
+    "#e6194b", "#4363d8", "#3cb44b", "#f58231", "#911eb4", "#9a6324", "#f032e6", "#469990",
+];
+
+/// Function to draw the history as an SVG Gantt chart with one row for every train
+/// Every trip is a bar from the departure to the arrival labelled with its stations, packages picked
+/// up are marked by a green triangle at the departure and packages dropped off by a red triangle at
+/// the arrival. Hovering over a bar or marker shows its details.
+pub fn to_svg(network: &Network) -> String {
+    let train = network.sorted_train();
+    let makespan = network.get_makespan().max(1);
+    let scale = WIDTH / makespan as f64;
+    let height = TOP + ROW * train.len() as f64 + 10.0;
+    let mut ret = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="10">"#,
+            LEFT + WIDTH + 20.0,
+            height
+        ),
+        r#"<rect width="100%" height="100%" fill="white"/>"#.to_string(),
+    ];
+    // Time axis with a grid line at every tick
+    let step = tick_step(makespan);
+    let mut time = 0;
+    while time <= makespan {
+        let x = LEFT + time as f64 * scale;
+        ret.push(format!(
+            r##"<line x1="{:.1}" y1="{}" x2="{:.1}" y2="{}" stroke="#ddd"/>"##,
+            x,
+            TOP - 5.0,
+            x,
+            height - 10.0
+        ));
+        ret.push(format!(
+            r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#,
+            x,
+            TOP - 10.0,
+            time
+        ));
+        time += step;
+    }
+    for (i, t) in train.iter().enumerate() {
+        let t = t.lock().unwrap();
+        let y = TOP + ROW * i as f64;
+        let colour = COLOUR[i % COLOUR.len()];
+        ret.push(format!(
+            r#"<text x="5" y="{:.1}" font-size="12">{}</text>"#,
+            y + ROW / 2.0 + 4.0,
+            escape(&t.get_name())
+        ));
+        for h in t.get_history().iter().filter(|h| h.has_arrived()) {
+            let x1 = LEFT + h.get_departure() as f64 * scale;
+            let x2 = LEFT + h.get_arrival() as f64 * scale;
+            let label = format!("{} → {}", h.get_from(), h.get_to());
+            ret.push(format!(
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" fill-opacity="0.7" stroke="{}"><title>{} {}, {}-{}</title></rect>"#,
+                x1,
+                y + 8.0,
+                (x2 - x1).max(1.0),
+                ROW - 16.0,
+                colour,
+                colour,
+                escape(&t.get_name()),
+                escape(&label),
+                h.get_departure(),
+                h.get_arrival()
+            ));
+            ret.push(format!(
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                (x1 + x2) / 2.0,
+                y + ROW / 2.0 + 4.0,
+                escape(&label)
+            ));
+            if !h.get_picked_up().is_empty() {
+                ret.push(marker(x1, y + 8.0, true, &h.get_picked_up(), &h.get_from()));
+            }
+            if !h.get_dropped_off().is_empty() {
+                ret.push(marker(
+                    x2,
+                    y + ROW - 8.0,
+                    false,
+                    &h.get_dropped_off(),
+                    &h.get_to(),
+                ));
+            }
+        }
+    }
+    ret.push("</svg>".to_string());
+    ret.join("\n") + "\n"
+}
+
+/// Function to draw the triangle marking packages picked up, on top of the bar, or dropped off, below it
+fn marker(x: f64, y: f64, pick_up: bool, package: &[String], node: &str) -> String {
+    let (tip, colour, action) = if pick_up {
+        (y + 6.0, "green", "picked up at")
+    } else {
+        (y - 6.0, "red", "dropped off at")
+    };
+    let base = if pick_up { y - 2.0 } else { y + 2.0 };
+    format!(
+        r#"<polygon points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" fill="{}"><title>{} {} {}</title></polygon>"#,
+        x - 5.0,
+        base,
+        x + 5.0,
+        base,
+        x,
+        tip,
+        colour,
+        escape(&package.join(", ")),
+        action,
+        escape(node)
+    )
+}
+
+/// Function to find a round time between the ticks of the axis so that there are about 10 ticks
+fn tick_step(makespan: u64) -> u64 {
+    let mut step = 1;
+    loop {
+        for m in [1, 2, 5] {
+            if makespan / (step * m) <= 10 {
+                return step * m;
+            }
+        }
+        step *= 10;
+    }
+}
+
+/// Function to escape the characters which have a meaning in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+
+    #[test]
+    fn axis_has_about_10_round_ticks() {
+        assert_eq!(tick_step(1), 1);
+        assert_eq!(tick_step(10), 1);
+        assert_eq!(tick_step(11), 2);
+        assert_eq!(tick_step(45), 5);
+        assert_eq!(tick_step(480), 50);
+    }
+
+    #[test]
+    fn trips_are_drawn_as_bars_with_markers() {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let mut network = create_network(
+            lines(&["A", "B<1>"]),
+            lines(&["E1,A,B<1>,10"]),
+            lines(&["Q1,6,A"]),
+            lines(&["K1,5,A,B<1>"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        network.simulate().unwrap();
+        let svg = to_svg(&network);
+        let svg = svg.lines().collect::<Vec<&str>>();
+        for line in [
+            r##"<rect x="80.0" y="38.0" width="1000.0" height="24.0" fill="#e6194b" fill-opacity="0.7" stroke="#e6194b"><title>Q1 A → B&lt;1&gt;, 0-10</title></rect>"##,
+            r#"<text x="580.0" y="54.0" text-anchor="middle">A → B&lt;1&gt;</text>"#,
+            r#"<polygon points="75.0,36.0 85.0,36.0 80.0,44.0" fill="green"><title>K1 picked up at A</title></polygon>"#,
+            r#"<polygon points="1075.0,64.0 1085.0,64.0 1080.0,56.0" fill="red"><title>K1 dropped off at B&lt;1&gt;</title></polygon>"#,
+        ] {
+            assert!(svg.contains(&line), "{}", line);
+        }
+        assert_eq!(svg.last(), Some(&"</svg>"));
+    }
+}
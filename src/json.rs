@@ -0,0 +1,398 @@
+use std::fmt::Display;
+
+/// The deepest nesting of arrays and objects accepted when reading a JSON text
+const MAX_DEPTH: usize = 64;
+
+/// A minimal JSON value, enough to read a scenario and to write the results of a simulation
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// The fields of an object in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => f.write_str(&b.to_string()),
+            // JSON has no infinity or NaN
+            Json::Number(n) if !n.is_finite() => f.write_str("null"),
+            Json::Number(n) => f.write_str(&n.to_string()),
+            Json::String(s) => write_string(f, s),
+            Json::Array(a) => {
+                f.write_str("[")?;
+                for (i, v) in a.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    v.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(o) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in o.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, k)?;
+                    f.write_str(":")?;
+                    v.fmt(f)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(v: Vec<T>) -> Self {
+        Json::Array(v.into_iter().map(|x| x.into()).collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Json::Null, |x| x.into())
+    }
+}
+
+impl Json {
+    /// Function to build an object from its fields
+    pub fn object(fields: Vec<(&str, Json)>) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    /// Function to read a JSON text
+    /// Returns the position and reason of the first error
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            text: text.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.text.len() {
+            return Err(parser.error("unexpected text after the value"));
+        }
+        Ok(value)
+    }
+
+    /// Function to set a field of an object, replacing the field of the same key if there is one
+    pub fn set(&mut self, key: &str, value: Json) {
+        if let Json::Object(o) = self {
+            match o.iter_mut().find(|(k, _)| k == key) {
+                Some((_, v)) => *v = value,
+                None => o.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    /// Function to get a field of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(o) => o.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Function to write a string with the characters JSON requires to be escaped
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => f.write_str(&format!("\\u{:04x}", c as u32))?,
+            c => f.write_str(&c.to_string())?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// A recursive descent parser over the characters of a JSON text
+struct Parser {
+    text: Vec<char>,
+    pos: usize,
+    /// Number of arrays and objects the parser is in
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, reason: &str) -> String {
+        format!("Invalid JSON at {}: {}", self.pos, reason)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.text.len() && self.text[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_nested(Self::parse_object),
+            Some('[') => self.parse_nested(Self::parse_array),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('t') => self.parse_word("true", Json::Bool(true)),
+            Some('f') => self.parse_word("false", Json::Bool(false)),
+            Some('n') => self.parse_word("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Function to parse an array or object, limiting how deep they are nested
+    fn parse_nested(
+        &mut self,
+        parse: impl Fn(&mut Self) -> Result<Json, String>,
+    ) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let ret = parse(self);
+        self.depth -= 1;
+        ret
+    }
+
+    fn parse_word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        let end = self.pos + word.len();
+        if end > self.text.len() || self.text[self.pos..end].iter().collect::<String>() != word {
+            return Err(self.error(&format!("expected {}", word)));
+        }
+        self.pos = end;
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                break;
+            }
+            self.pos += 1;
+        }
+        let number = self.text[start..self.pos].iter().collect::<String>();
+        number
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut ret = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(ret),
+                '\\' => {
+                    let e = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match e {
+                        '"' | '\\' | '/' => ret.push(e),
+                        'n' => ret.push('\n'),
+                        'r' => ret.push('\r'),
+                        't' => ret.push('\t'),
+                        'b' => ret.push('\u{8}'),
+                        'f' => ret.push('\u{c}'),
+                        'u' => {
+                            let end = self.pos + 4;
+                            if end > self.text.len() {
+                                return Err(self.error("invalid escape"));
+                            }
+                            let code = self.text[self.pos..end].iter().collect::<String>();
+                            let code = u32::from_str_radix(&code, 16)
+                                .map_err(|_| self.error("invalid escape"))?;
+                            ret.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.pos = end;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => ret.push(c),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut ret = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(ret));
+        }
+        loop {
+            ret.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(ret));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut ret = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(ret));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            ret.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(ret));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_is_written_and_read_back() {
+        let value = Json::object(vec![
+            ("name", "K1 \"fragile\"\n".into()),
+            ("size", 5u64.into()),
+            ("load", 0.5.into()),
+            ("late", false.into()),
+            ("delivered_at", None::<u64>.into()),
+            ("stops", vec!["A", "B"].into()),
+        ]);
+        let text = value.to_string();
+        assert_eq!(
+            text,
+            r#"{"name":"K1 \"fragile\"\n","size":5,"load":0.5,"late":false,"delivered_at":null,"stops":["A","B"]}"#
+        );
+        assert_eq!(Json::parse(&text), Ok(value));
+    }
+
+    #[test]
+    fn number_which_is_not_finite_is_written_as_null() {
+        let value: Json = vec![f64::NAN, f64::INFINITY, -f64::INFINITY].into();
+        assert_eq!(value.to_string(), "[null,null,null]");
+    }
+
+    #[test]
+    fn text_which_is_not_valid_is_reported() {
+        assert_eq!(
+            Json::parse(r#"{"stations": ["A",]}"#),
+            Err("Invalid JSON at 18: expected a value".to_string())
+        );
+        assert_eq!(
+            Json::parse("[1] 2"),
+            Err("Invalid JSON at 4: unexpected text after the value".to_string())
+        );
+        let deep = "[".repeat(100_000) + &"]".repeat(100_000);
+        assert_eq!(
+            Json::parse(&deep),
+            Err("Invalid JSON at 64: nested too deeply".to_string())
+        );
+        let nested = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(Json::parse(&nested).is_ok());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{json::Json, network::Network};
+
+/// The key performance indicators of a simulation, computed from the history and the packages
+pub struct Kpi {
+    makespan: u64,
+    train: Vec<TrainKpi>,
+    package: Vec<PackageKpi>,
+}
+
+/// The performance of one train
+/// Distances are measured in journey time of the edges, as the edges have no length
+struct TrainKpi {
+    name: String,
+    /// Journey time of every edge travelled, as it was when the trip started
+    distance: i64,
+    /// Journey time of the edges travelled without any package
+    empty_distance: i64,
+    /// Time from the departures to the arrivals, including delays on the edges
+    travel_time: u64,
+    /// Time until the makespan not spent travelling, e.g. handling packages, waiting or after the last trip
+    idle_time: u64,
+    /// Time spent queueing for a free platform, waiting for a free line, waiting for a closed line to
+    /// open and resting, as shown in the output
+    queue_time: u64,
+    wait_time: u64,
+    delay_time: u64,
+    rest_time: u64,
+    /// Number of stops at which packages were picked up or dropped off
+    stop: u64,
+    /// Share of the capacity used, weighted by the travel time of every trip
+    mean_utilisation: f64,
+    max_utilisation: f64,
+}
+
+/// The performance of one package
+struct PackageKpi {
+    name: String,
+    /// Time from the release until the package, or its first part, is picked up
+    wait_time: Option<u64>,
+    /// Time from the first pick up until the package is delivered
+    transit_time: Option<u64>,
+    delivered_at: Option<u64>,
+}
+
+impl Kpi {
+    pub fn new(network: &Network) -> Self {
+        let makespan = network.get_makespan();
+        let mut train = vec![];
+        for t in network.sorted_train() {
+            let t = t.lock().unwrap();
+            let capacity = t.get_capacity();
+            let mut kpi = TrainKpi {
+                name: t.get_name(),
+                distance: 0,
+                empty_distance: 0,
+                travel_time: 0,
+                idle_time: 0,
+                queue_time: 0,
+                wait_time: 0,
+                delay_time: 0,
+                rest_time: 0,
+                stop: 0,
+                mean_utilisation: 0.0,
+                max_utilisation: 0.0,
+            };
+            let mut weighted_utilisation = 0.0;
+            let history = t
+                .get_history()
+                .into_iter()
+                .filter(|h| h.has_arrived())
+                .collect::<Vec<_>>();
+            for (i, h) in history.iter().enumerate() {
+                kpi.queue_time += h.get_queue();
+                kpi.wait_time += h.get_wait();
+                kpi.delay_time += h.get_delay();
+                kpi.rest_time += h.get_rest();
+                let journey_time = network
+                    .get_node(h.get_from())
+                    .lock()
+                    .unwrap()
+                    .get_edge(h.get_to())
+                    .lock()
+                    .unwrap()
+                    .get_journey_time_at(h.get_departure());
+                let travel_time = h.get_arrival() - h.get_departure();
+                let utilisation = h.get_load().utilisation(&capacity);
+                kpi.distance += journey_time;
+                if utilisation == 0.0 {
+                    kpi.empty_distance += journey_time;
+                }
+                kpi.travel_time += travel_time;
+                weighted_utilisation += utilisation * travel_time as f64;
+                kpi.max_utilisation = kpi.max_utilisation.max(utilisation);
+                // A stop drops off packages at the end of a trip or picks them up for the next one
+                if i == 0 && !h.get_picked_up().is_empty() {
+                    kpi.stop += 1;
+                }
+                let next = history.get(i + 1);
+                if !h.get_dropped_off().is_empty()
+                    || next.is_some_and(|n| !n.get_picked_up().is_empty())
+                {
+                    kpi.stop += 1;
+                }
+            }
+            kpi.idle_time = makespan.saturating_sub(kpi.travel_time);
+            if kpi.travel_time > 0 {
+                kpi.mean_utilisation = weighted_utilisation / kpi.travel_time as f64;
+            }
+            train.push(kpi);
+        }
+        let history = network.get_history();
+        let mut package = vec![];
+        for p in network.sorted_package() {
+            let p = p.lock().unwrap();
+            let name = p.get_name();
+            let part = format!("{}/", name);
+            // A divisible package is picked up as soon as its first part is
+            let picked_up_at = history
+                .iter()
+                .find(|h| {
+                    h.get_picked_up()
+                        .iter()
+                        .any(|x| *x == name || x.starts_with(&part))
+                })
+                .map(|h| h.get_departure());
+            package.push(PackageKpi {
+                name,
+                wait_time: picked_up_at.map(|t| t.saturating_sub(p.get_release())),
+                transit_time: picked_up_at
+                    .zip(p.get_delivered_at())
+                    .map(|(from, until)| until - from),
+                delivered_at: p.get_delivered_at(),
+            });
+        }
+        Self {
+            makespan,
+            train,
+            package,
+        }
+    }
+
+    /// Function to describe the indicators as tables
+    pub fn table(&self) -> Vec<String> {
+        let mut ret = vec![format!("Makespan: {}", self.makespan)];
+        ret.push(format!(
+            "{:<10} {:>9} {:>9} {:>9} {:>9} {:>6} {:>6} {:>6} {:>6} {:>6} {:>10} {:>10}",
+            "Train",
+            "Distance",
+            "Empty",
+            "Travel",
+            "Idle",
+            "Queue",
+            "Wait",
+            "Delay",
+            "Rest",
+            "Stops",
+            "Mean load",
+            "Max load"
+        ));
+        for t in self.train.iter() {
+            ret.push(format!(
+                "{:<10} {:>9} {:>9} {:>9} {:>9} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9.1}% {:>9.1}%",
+                t.name,
+                t.distance,
+                t.empty_distance,
+                t.travel_time,
+                t.idle_time,
+                t.queue_time,
+                t.wait_time,
+                t.delay_time,
+                t.rest_time,
+                t.stop,
+                t.mean_utilisation * 100.0,
+                t.max_utilisation * 100.0
+            ));
+        }
+        ret.push(format!(
+            "{:<10} {:>9} {:>11}",
+            "Package", "Wait", "In transit"
+        ));
+        for p in self.package.iter() {
+            ret.push(format!(
+                "{:<10} {:>9} {:>11}",
+                p.name,
+                p.wait_time.map_or("-".to_string(), |x| x.to_string()),
+                p.transit_time.map_or("-".to_string(), |x| x.to_string())
+            ));
+        }
+        ret
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("makespan", self.makespan.into()),
+            (
+                "trains",
+                Json::Array(
+                    self.train
+                        .iter()
+                        .map(|t| {
+                            Json::object(vec![
+                                ("name", t.name.clone().into()),
+                                ("distance", (t.distance as f64).into()),
+                                ("empty_distance", (t.empty_distance as f64).into()),
+                                ("travel_time", t.travel_time.into()),
+                                ("idle_time", t.idle_time.into()),
+                                ("queue_time", t.queue_time.into()),
+                                ("wait_time", t.wait_time.into()),
+                                ("delay_time", t.delay_time.into()),
+                                ("rest_time", t.rest_time.into()),
+                                ("stops", t.stop.into()),
+                                ("mean_utilisation", t.mean_utilisation.into()),
+                                ("max_utilisation", t.max_utilisation.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "packages",
+                Json::Array(
+                    self.package
+                        .iter()
+                        .map(|p| {
+                            Json::object(vec![
+                                ("name", p.name.clone().into()),
+                                ("wait_time", p.wait_time.into()),
+                                ("transit_time", p.transit_time.into()),
+                                ("delivered_at", p.delivered_at.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+
+    #[test]
+    fn travel_empty_and_loaded_is_measured() {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let mut network = create_network(
+            lines(&["A", "B,dwell=1", "C"]),
+            lines(&["E1,A,B,5", "E2,B,C,3"]),
+            lines(&["Q1,10,A", "Q2,10,C"]),
+            lines(&["K1,5,B,C", "K2,4,B,C,release=2"]),
+        );
+        network.calculate_shortest_distance_between_packages();
+        network.simulate().unwrap();
+        assert_eq!(
+            Kpi::new(&network).table(),
+            vec![
+                "Makespan: 7",
+                "Train       Distance     Empty    Travel      Idle  Queue   Wait  Delay   Rest  Stops  Mean load   Max load",
+                "Q1                 5         5         5         2      0      0      0      0      0       0.0%       0.0%",
+                "Q2                 6         3         6         1      0      0      0      0      2      45.0%      90.0%",
+                "Package         Wait  In transit",
+                "K1                 4           3",
+                "K2                 2           3",
+            ]
+        );
+        let json = Kpi::new(&network).to_json();
+        let q2 = &json.get("trains").unwrap().as_array().unwrap()[1];
+        assert_eq!(q2.get("name").unwrap().as_str(), Some("Q2"));
+        assert_eq!(q2.get("empty_distance").unwrap().as_f64(), Some(3.0));
+        assert_eq!(q2.get("mean_utilisation").unwrap().as_f64(), Some(0.45));
+        let k1 = &json.get("packages").unwrap().as_array().unwrap()[0];
+        assert_eq!(k1.get("delivered_at").unwrap().as_f64(), Some(7.0));
+    }
+}
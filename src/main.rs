@@ -1,15 +1,43 @@
-use std::io::{stdin, stdout, Write};
+use std::{
+    fs,
+    io::{stdin, stdout, Write},
+    time::Duration,
+};
 
+use animation::Animation;
+use dimension::Dimensions;
+use dispatcher::Dispatcher;
 use edge::Edge;
+use json::Json;
+use kpi::Kpi;
+use monte_carlo::MonteCarlo;
 use network::Network;
 use node::Node;
 use package::Package;
-use train::Train;
+use parse::{parse_list, parse_options, parse_periods};
+use repl::Repl;
+use server::Server;
+use simulator::Simulator;
+use train::{Train, TrainClass};
 
+pub mod animation;
+pub mod diff;
+pub mod dimension;
+pub mod dispatcher;
+pub mod dot;
 pub mod edge;
+pub mod gantt;
+pub mod json;
+pub mod kpi;
+pub mod monte_carlo;
 pub mod network;
 pub mod node;
 pub mod package;
+pub mod parse;
+pub mod random;
+pub mod repl;
+pub mod server;
+pub mod simulator;
 pub mod train;
 
 // Function to get the input from terminal interactively
@@ -40,7 +68,7 @@ fn get_input() -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
         node.push(input_string.clone());
     }
 
-    println!("");
+    println!();
     input_string.clear();
     print!("Number of edges: ");
     stdout().flush().unwrap();
@@ -67,7 +95,7 @@ fn get_input() -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
         edge.push(input_string.clone());
     }
 
-    println!("");
+    println!();
     input_string.clear();
     print!("Number of deliveries to be performed: ");
     stdout().flush().unwrap();
@@ -94,7 +122,7 @@ fn get_input() -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
         package.push(input_string.clone());
     }
 
-    println!("");
+    println!();
     input_string.clear();
     print!("Number of trains: ");
     stdout().flush().unwrap();
@@ -128,6 +156,57 @@ fn get_input() -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
     (node, edge, package, trains)
 }
 
+// Function to read the input in the same format as it is entered, a count followed by that many lines
+// for the stations, edges, packages and trains, e.g. from a snapshot file
+fn read_input(
+    lines: &mut impl Iterator<Item = String>,
+) -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
+    let mut section = vec![];
+    for _ in 0..4 {
+        let count: usize = lines.next().unwrap().trim().parse().unwrap();
+        section.push(lines.by_ref().take(count).collect::<Vec<String>>());
+    }
+    let train = section.pop().unwrap();
+    let package = section.pop().unwrap();
+    let edge = section.pop().unwrap();
+    let node = section.pop().unwrap();
+    (node, edge, package, train)
+}
+
+// Function to write the input in the format read by `read_input`
+fn write_input(
+    node: &[String],
+    edge: &[String],
+    package: &[String],
+    train: &[String],
+) -> Vec<String> {
+    let mut ret = vec![];
+    for section in [node, edge, package, train] {
+        ret.push(section.len().to_string());
+        ret.extend(section.iter().cloned());
+    }
+    ret
+}
+
+// Function to parse a package line, also used for the packages ordered during the simulation
+fn create_package(p: &str, node_dict: &Network) -> Package {
+    let package_info = p.split(",").map(|x| x.to_string()).collect::<Vec<String>>();
+    let options = parse_options(&package_info[4..]);
+    let mut new_package = Package::new(
+        package_info[0].clone(),
+        Dimensions::parse(&package_info[1]),
+        node_dict.get_node(package_info[2].clone()),
+        node_dict.get_node(package_info[3].clone()),
+    );
+    new_package.set_requirement(parse_list(options.get("requires")));
+    new_package.set_divisible(options.contains_key("divisible"));
+    new_package.set_deadline(options.get("deadline").map(|x| x.parse().unwrap()));
+    if let Some(release) = options.get("release") {
+        new_package.set_release(release.parse().unwrap());
+    }
+    new_package
+}
+
 // Function to parse the input array of string to create a new network instance
 fn create_network(
     node: Vec<String>,
@@ -137,7 +216,21 @@ fn create_network(
 ) -> Network {
     let mut nodes = vec![];
     for n in node {
-        nodes.push(Node::new(n));
+        let node_info = n.split(",").map(|x| x.to_string()).collect::<Vec<String>>();
+        let options = parse_options(&node_info[1..]);
+        let mut new_node = Node::new(node_info[0].clone());
+        new_node.set_service_time(
+            options.get("dwell").map_or(0, |x| x.parse().unwrap()),
+            options.get("handling").map_or(0, |x| x.parse().unwrap()),
+        );
+        new_node.set_platform(options.get("platforms").map(|x| x.parse().unwrap()));
+        new_node.set_hub(options.contains_key("hub"));
+        // A station given `refuel` without a time refuels a train instantly
+        new_node.set_refuel_time(options.get("refuel").map(|x| match x.as_str() {
+            "" => 0,
+            _ => x.parse().unwrap(),
+        }));
+        nodes.push(new_node);
     }
     let mut network = Network::new();
     network.initialize_node(nodes);
@@ -145,41 +238,74 @@ fn create_network(
     let mut edges = vec![];
     for e in edge {
         let edge_info = e.split(",").map(|x| x.to_string()).collect::<Vec<String>>();
-        edges.push(Edge::new(
+        let options = parse_options(&edge_info[4..]);
+        let mut new_edge = Edge::new(
             edge_info[0].to_string(),
             node_dict.get_node(edge_info[1].clone()),
             node_dict.get_node(edge_info[2].clone()),
             edge_info[3].parse().unwrap(),
-        ));
+        );
+        new_edge.set_capacity(options.get("capacity").map(|x| x.parse().unwrap()));
+        new_edge.set_banned(parse_list(options.get("ban")));
+        new_edge.set_closure(parse_periods(options.get("closed")));
+        new_edge.set_random_delay(options.get("delay").map(|x| {
+            let (low, high) = x.split_once(':').unwrap();
+            (low.parse().unwrap(), high.parse().unwrap())
+        }));
+        new_edge.set_retime(
+            parse_periods(options.get("journey"))
+                .into_iter()
+                .map(|(t, j)| (t, j as i64))
+                .collect(),
+        );
         let mut r_name = edge_info[0].to_string();
         r_name.push_str(" R");
-        edges.push(Edge::new(
-            r_name,
-            node_dict.get_node(edge_info[2].clone()),
-            node_dict.get_node(edge_info[1].clone()),
-            edge_info[3].parse().unwrap(),
-        ));
+        // Both directions share the same track
+        edges.push(new_edge.reverse(r_name));
+        edges.push(new_edge);
     }
     network.initialize_edge(edges);
     let mut trains = vec![];
     for t in train {
         let train_info = t.split(",").map(|x| x.to_string()).collect::<Vec<String>>();
-        trains.push(Train::new(
+        let options = parse_options(&train_info[3..]);
+        let mut new_train = Train::new(
             train_info[0].clone(),
-            train_info[1].parse().unwrap(),
+            Dimensions::parse(&train_info[1]),
             node_dict.get_node(train_info[2].clone()),
-        ));
+        );
+        new_train.set_tags(parse_list(options.get("tags")));
+        new_train.set_lifo(options.contains_key("lifo"));
+        new_train.set_fuel(
+            options.get("fuel").map(|x| x.parse().unwrap()),
+            options.get("burn").map_or(1.0, |x| x.parse().unwrap()),
+            options.get("burn_load").map_or(0.0, |x| x.parse().unwrap()),
+        );
+        let speed = options.get("speed").map_or(1.0, |x| x.parse().unwrap());
+        // The travel time is divided by the speed
+        assert!(speed > 0.0, "The speed of a train must be greater than 0");
+        new_train.set_class(TrainClass {
+            name: options.get("class").cloned().unwrap_or_default(),
+            speed,
+        });
+        new_train.set_shift(
+            options.get("shift").map(|x| x.parse().unwrap()),
+            options.get("rest").map(|x| {
+                let (after, duration) = x.split_once(':').unwrap();
+                (after.parse().unwrap(), duration.parse().unwrap())
+            }),
+        );
+        // A train given `home` without a station returns to where it started
+        new_train.set_home(options.get("home").map(|h| match h.as_str() {
+            "" => node_dict.get_node(train_info[2].clone()),
+            _ => node_dict.get_node(h.clone()),
+        }));
+        trains.push(new_train);
     }
     network.initialize_train(trains);
     let mut packages = vec![];
     for p in package {
-        let package_info = p.split(",").map(|x| x.to_string()).collect::<Vec<String>>();
-        packages.push(Package::new(
-            package_info[0].clone(),
-            package_info[1].parse().unwrap(),
-            node_dict.get_node(package_info[2].clone()),
-            node_dict.get_node(package_info[3].clone()),
-        ));
+        packages.push(create_package(&p, &node_dict));
     }
     network.initialize_package(packages);
     network
@@ -291,14 +417,231 @@ fn create_network(
 //     network.print_history();
 // }
 
+// Function to simulate until the given time and then print the state of the trains at every event
+fn trace(simulator: &mut Simulator, from: u64) -> Result<(), String> {
+    let mut running = simulator.run_until(from)?;
+    loop {
+        println!("Time {}:", simulator.get_time());
+        for t in simulator.get_train_state() {
+            println!(
+                "  {} at {}{}, load {}, packages {:?}, route {:?}{}",
+                t.name,
+                t.location,
+                if t.on_edge {
+                    format!(" for {}", t.time_on_edge)
+                } else {
+                    "".to_string()
+                },
+                t.load,
+                t.package,
+                t.route,
+                if t.end_trip { ", ended" } else { "" }
+            );
+        }
+        if !running {
+            return Ok(());
+        }
+        running = simulator.run_until_event()?;
+    }
+}
+
+// Function to simulate until the given time, save the input and the state of the simulation to a file
+// and then simulate until the end
+fn snapshot(
+    simulator: &mut Simulator,
+    time: u64,
+    file: &str,
+    input: Vec<String>,
+) -> Result<(), String> {
+    simulator.run_until(time)?;
+    let mut lines = input;
+    lines.extend(simulator.get_network().save_state());
+    fs::write(file, lines.join("\n") + "\n").unwrap();
+    println!(
+        "Snapshot at time {} saved to {}",
+        simulator.get_time(),
+        file
+    );
+    simulator.run()
+}
+
+// Function to run the simulation as a dispatcher, reading the events from the file or else from the
+// rest of the terminal input, and printing the history as it happens
+fn dispatch(
+    simulator: Simulator,
+    interval: u64,
+    file: Option<&String>,
+) -> (Network, Result<(), String>) {
+    let mut dispatcher = Dispatcher::new(simulator, interval);
+    println!();
+    println!("--------------------------------------------------------------------");
+    println!("Output:");
+    let result = match file {
+        Some(f) => {
+            let text = fs::read_to_string(f).unwrap();
+            dispatcher.run(text.lines().map(|x| x.to_string()), create_package)
+        }
+        None => dispatcher.run(stdin().lines().map(|x| x.unwrap()), create_package),
+    };
+    (dispatcher.get_network().clone(), result)
+}
+
+// Function to read the result of a simulation from a file, either saved by the `result` mode or
+// a scenario in the format of a snapshot file without the state, which is simulated first
+fn load_result(file: &str) -> Json {
+    let text = fs::read_to_string(file).unwrap();
+    if let Ok(result) = Json::parse(&text) {
+        return result;
+    }
+    let (station, edge, deliveries, train) = read_input(&mut text.lines().map(|x| x.to_string()));
+    let mut network = create_network(station, edge, train, deliveries);
+    network.calculate_shortest_distance_between_packages();
+    let error = network.simulate().err();
+    let mut result = network.to_json();
+    result.set("error", error.into());
+    result
+}
+
 // Main function to run the project
+// `monte-carlo <runs> <seed>` simulates the scenario many times with random delays instead
+// `trace <time>` prints the state of the trains at every event from this time on
+// `snapshot <time> <file>` saves the state of the simulation at this time to a file
+// `restore <file>` continues the simulation saved to a file instead of reading the input
+// `dispatch <interval> [file]` reads timestamped events while simulating and replans every interval
+// `serve [address]` runs an HTTP server which simulates the scenarios posted to it
+// `dot <file> [routes]` saves the network, with the routes of the trains if given, as a Graphviz graph
+// `animate [milliseconds] [events]` shows the trains moving in the terminal at every step or event
+// `kpi <file>` saves the KPI report as JSON
+// `gantt <file>` saves the history as an SVG Gantt chart
+// `result <file>` saves the history, the KPI report and the problems found as JSON
+// `diff <file> <file>` compares two results or scenarios instead of reading the input
+// `repl` reads commands to change the scenario and simulate it again after the input
 fn main() {
-    let (station, edge, deliveries, train) = get_input();
+    let args = std::env::args().collect::<Vec<String>>();
+    let mode = args.get(1).map(|x| x.as_str());
+    if mode == Some("diff") {
+        for d in diff::diff(&load_result(&args[2]), &load_result(&args[3])) {
+            println!("{}", d);
+        }
+        return;
+    }
+    if mode == Some("serve") {
+        let mut server = Server::new(args.get(2).map_or("127.0.0.1:8080", |x| x.as_str()));
+        println!("Listening on http://{}", server.get_address());
+        server.run(create_network);
+        return;
+    }
+    let mut state = vec![];
+    let (station, edge, deliveries, train) = match mode {
+        Some("restore") => {
+            let text = fs::read_to_string(&args[2]).unwrap();
+            let mut lines = text.lines().map(|x| x.to_string());
+            let input = read_input(&mut lines);
+            state = lines.collect();
+            input
+        }
+        _ => get_input(),
+    };
+    let input = write_input(&station, &edge, &deliveries, &train);
+    if mode == Some("repl") {
+        println!();
+        Repl::new(station, edge, deliveries, train).run(stdin().lock(), create_network);
+        return;
+    }
+    if mode == Some("monte-carlo") {
+        let runs = args.get(2).map_or(100, |x| x.parse().unwrap());
+        let seed = args.get(3).map_or(0, |x| x.parse().unwrap());
+        let monte_carlo = MonteCarlo::new(runs, seed);
+        let report = monte_carlo.run(|| {
+            create_network(
+                station.clone(),
+                edge.clone(),
+                train.clone(),
+                deliveries.clone(),
+            )
+        });
+        println!();
+        println!("--------------------------------------------------------------------");
+        println!("Monte Carlo:");
+        for r in report {
+            println!("{}", r);
+        }
+        return;
+    }
     let mut network = create_network(station, edge, train, deliveries);
+    for d in network.diagnose() {
+        println!("Warning: {}", d);
+    }
     network.calculate_shortest_distance_between_packages();
-    network.simulate();
-    println!("");
+    if mode == Some("restore") {
+        if let Err(e) = network.restore_state(&state) {
+            println!("The snapshot is not valid: {}", e);
+            return;
+        }
+    }
+    let mut simulator = Simulator::new(network);
+    if mode == Some("dispatch") {
+        let interval = args.get(2).map_or(0, |x| x.parse().unwrap());
+        let (network, result) = dispatch(simulator, interval, args.get(3));
+        if let Err(e) = result {
+            println!("Simulation stopped: {}", e);
+        }
+        for r in network.result_report() {
+            println!("{}", r);
+        }
+        return;
+    }
+    let result = match mode {
+        Some("snapshot") => snapshot(&mut simulator, args[2].parse().unwrap(), &args[3], input),
+        Some("animate") => Animation::new(
+            Duration::from_millis(args.get(2).map_or(100, |x| x.parse().unwrap())),
+            args.get(3).map(|x| x.as_str()) == Some("events"),
+        )
+        .run(&mut simulator),
+        Some("trace") => trace(
+            &mut simulator,
+            args.get(2).map_or(0, |x| x.parse().unwrap()),
+        ),
+        _ => simulator.run(),
+    };
+    let network = simulator.get_network();
+    println!();
     println!("--------------------------------------------------------------------");
     println!("Output:");
     network.print_history();
+    if let Err(e) = &result {
+        println!("Simulation stopped: {}", e);
+    }
+    for r in network.result_report() {
+        println!("{}", r);
+    }
+    for v in network.validate_plan() {
+        println!("Invalid plan: {}", v);
+    }
+    let kpi = Kpi::new(network);
+    println!();
+    println!("--------------------------------------------------------------------");
+    println!("KPI:");
+    for k in kpi.table() {
+        println!("{}", k);
+    }
+    if mode == Some("result") {
+        let mut json = network.to_json();
+        json.set("error", result.err().into());
+        fs::write(&args[2], json.to_string() + "\n").unwrap();
+        println!("Result saved to {}", args[2]);
+    }
+    if mode == Some("kpi") {
+        fs::write(&args[2], kpi.to_json().to_string() + "\n").unwrap();
+        println!("KPI saved to {}", args[2]);
+    }
+    if mode == Some("dot") {
+        let route = args.get(3).map(|x| x.as_str()) == Some("routes");
+        fs::write(&args[2], dot::to_dot(network, route)).unwrap();
+        println!("Graph saved to {}", args[2]);
+    }
+    if mode == Some("gantt") {
+        fs::write(&args[2], gantt::to_svg(network)).unwrap();
+        println!("Gantt chart saved to {}", args[2]);
+    }
 }
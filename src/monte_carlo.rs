@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use crate::network::Network;
+
+/// Runs the same scenario many times with different random delays on the edges
+/// and summarises how robust the plan is
+pub struct MonteCarlo {
+    /// Number of times the scenario is simulated
+    runs: u64,
+    /// Seed of the first run, every following run uses the next seed
+    seed: u64,
+}
+
+/// The results of one package over all the runs which were not stopped early
+#[derive(Default)]
+struct PackageResult {
+    /// Delivery time of every run in which the package was delivered
+    delivered_at: Vec<u64>,
+    /// Deadline of the package, if any
+    deadline: Option<u64>,
+    /// Number of runs in which the package was delivered late or not at all
+    late: u64,
+}
+
+impl MonteCarlo {
+    pub fn new(runs: u64, seed: u64) -> Self {
+        Self { runs, seed }
+    }
+
+    /// Function to simulate the scenario created by `build` once for every run
+    /// Returns the report of the makespan, the delivery time of every package and
+    /// the probability of missing the deadlines
+    /// Runs stopped early, e.g. by a deadlock, are only counted by the reason they stopped
+    /// and left out of the statistics
+    pub fn run(&self, build: impl Fn() -> Network) -> Vec<String> {
+        let mut stopped: BTreeMap<String, u64> = BTreeMap::new();
+        let mut makespan = vec![];
+        let mut package: BTreeMap<String, PackageResult> = BTreeMap::new();
+        for i in 0..self.runs {
+            let mut network = build();
+            network.set_seed(self.seed.wrapping_add(i));
+            network.calculate_shortest_distance_between_packages();
+            if let Err(e) = network.simulate() {
+                *stopped.entry(e).or_default() += 1;
+                continue;
+            }
+            makespan.push(network.get_makespan());
+            for p in network.sorted_package() {
+                let p = p.lock().unwrap();
+                let result = package.entry(p.get_name()).or_default();
+                result.deadline = p.get_deadline();
+                if let Some(time) = p.get_delivered_at() {
+                    result.delivered_at.push(time);
+                }
+                if p.is_late() {
+                    result.late += 1;
+                }
+            }
+        }
+        let completed = makespan.len();
+        let mut ret = vec![format!(
+            "Runs: {}, completed: {}, stopped early: {}",
+            self.runs,
+            completed,
+            self.runs - completed as u64
+        )];
+        for (reason, count) in stopped {
+            ret.push(format!("Stopped early in {} runs: {}", count, reason));
+        }
+        ret.push(format!("Makespan: {}", summarise(&mut makespan)));
+        for (name, mut result) in package {
+            let mut line = format!(
+                "Package {}: delivered in {} of {} completed runs",
+                name,
+                result.delivered_at.len(),
+                completed
+            );
+            if !result.delivered_at.is_empty() {
+                line.push_str(&format!(
+                    ", delivery time {}",
+                    summarise(&mut result.delivered_at)
+                ));
+            }
+            if let Some(deadline) = result.deadline {
+                line.push_str(&format!(
+                    ", missed deadline {} in {:.1}% of runs",
+                    deadline,
+                    result.late as f64 * 100.0 / completed as f64
+                ));
+            }
+            ret.push(line);
+        }
+        ret
+    }
+}
+
+/// Function to describe the distribution of some values by the mean, the extremes and the percentiles
+fn summarise(values: &mut [u64]) -> String {
+    if values.is_empty() {
+        return "-".to_string();
+    }
+    values.sort();
+    let mean = values.iter().sum::<u64>() as f64 / values.len() as f64;
+    format!(
+        "mean {:.1}, min {}, 50% {}, 95% {}, max {}",
+        mean,
+        values[0],
+        percentile(values, 0.5),
+        percentile(values, 0.95),
+        values[values.len() - 1]
+    )
+}
+
+/// Function to find the nearest-rank percentile of sorted values
+fn percentile(values: &[u64], q: f64) -> u64 {
+    let rank = (q * values.len() as f64).ceil() as usize;
+    values[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::create_network;
+
+    /// Function to build a network from the lines of the input
+    fn network(node: &[&str], edge: &[&str], package: &[&str], train: &[&str]) -> Network {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        create_network(lines(node), lines(edge), lines(train), lines(package))
+    }
+
+    #[test]
+    fn same_seed_draws_the_same_delays() {
+        let build = || {
+            network(
+                &["A", "B", "C"],
+                &["E1,A,B,3,delay=0:4", "E2,B,C,3,delay=0:2"],
+                &["K1,5,A,C,deadline=8", "K2,5,A,B,deadline=5"],
+                &["Q1,20,A"],
+            )
+        };
+        let report = MonteCarlo::new(20, 42).run(build);
+        assert_eq!(report, MonteCarlo::new(20, 42).run(build));
+        assert_eq!(
+            report,
+            vec![
+                "Runs: 20, completed: 20, stopped early: 0",
+                "Makespan: mean 9.2, min 7, 50% 9, 95% 12, max 12",
+                "Package K1: delivered in 20 of 20 completed runs, delivery time mean 9.2, min 7, 50% 9, 95% 12, max 12, missed deadline 8 in 65.0% of runs",
+                "Package K2: delivered in 20 of 20 completed runs, delivery time mean 4.9, min 3, 50% 5, 95% 7, max 7, missed deadline 5 in 35.0% of runs",
+            ]
+        );
+    }
+
+    #[test]
+    fn stopped_runs_are_left_out_of_the_statistics() {
+        // Every other run the package cannot be reached
+        let run = Cell::new(0);
+        let build = || {
+            run.set(run.get() + 1);
+            let edge: &[&str] = if run.get() % 2 == 0 {
+                &[]
+            } else {
+                &["E1,A,B,3"]
+            };
+            network(&["A", "B"], edge, &["K1,5,A,B,deadline=2"], &["Q1,20,A"])
+        };
+        assert_eq!(
+            MonteCarlo::new(4, 0).run(build),
+            vec![
+                "Runs: 4, completed: 2, stopped early: 2",
+                "Stopped early in 2 runs: Some packages are not reachable",
+                "Makespan: mean 3.0, min 3, 50% 3, 95% 3, max 3",
+                "Package K1: delivered in 2 of 2 completed runs, delivery time mean 3.0, min 3, 50% 3, 95% 3, max 3, missed deadline 2 in 100.0% of runs",
+            ]
+        );
+    }
+}
@@ -6,10 +6,15 @@ use std::{
 use rudac::heap::FibonacciHeap;
 
 use crate::{
+    dimension::Dimensions,
     edge::Edge,
-    node::Node,
+    json::Json,
+    kpi::Kpi,
+    node::{Node, ShortestPath},
     package::Package,
-    train::{History, Location, Train},
+    parse::{parse_field, parse_options, parse_sequence},
+    random::Random,
+    train::{History, Location, Train, TrainClass},
 };
 
 /// This is a struct to hold the hashmap to every instance contained in itself
@@ -20,10 +25,18 @@ pub struct Network {
     edge: HashMap<String, Arc<Mutex<Edge>>>,
     train: HashMap<String, Arc<Mutex<Train>>>,
     package: HashMap<String, Arc<Mutex<Package>>>,
+    /// Packages which are not released yet, ordered by their release time
+    pending: Vec<Arc<Mutex<Package>>>,
     /// The overall time simulated in this network instance
     time: u64,
 }
 
+impl Default for Network {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Network {
     pub fn new() -> Self {
         Self {
@@ -31,6 +44,7 @@ impl Network {
             edge: HashMap::new(),
             train: HashMap::new(),
             package: HashMap::new(),
+            pending: vec![],
             time: 0,
         }
     }
@@ -48,6 +62,10 @@ impl Network {
         self.node.get(&name).unwrap().clone()
     }
 
+    pub fn has_node(&self, name: &str) -> bool {
+        self.node.contains_key(name)
+    }
+
     /// Function to register new edges in this network graph
     /// The start node of the edges will also registering this edge in it
     pub fn initialize_edge(&mut self, e: Vec<Edge>) {
@@ -78,6 +96,11 @@ impl Network {
                 Location::Node(n) => n.lock().unwrap().add_train(new_train.clone()),
                 _ => panic!("Train is not initalized at a node."),
             };
+            // The depot has to be a critical node so that the train can find its way back
+            let home = new_train.lock().unwrap().get_home();
+            if let Some(home) = home {
+                home.lock().unwrap().set_critical();
+            }
         }
         self.train = trains;
     }
@@ -86,6 +109,10 @@ impl Network {
         self.train.get(&name).unwrap().clone()
     }
 
+    pub fn has_train(&self, name: &str) -> bool {
+        self.train.contains_key(name)
+    }
+
     /// Function to register new packages in this network graph
     /// The nodes at which it is alocated and will be dropped at will be registered by this package as well
     pub fn initialize_package(&mut self, p: Vec<Package>) {
@@ -94,27 +121,129 @@ impl Network {
             let new_package = Arc::new(Mutex::new(package));
             packages.insert(new_package.lock().unwrap().get_name(), new_package.clone());
             let node = new_package.lock().unwrap().get_start_node();
-            node.lock()
-                .unwrap()
-                .add_pick_up_package(new_package.clone());
+            // A package released later is only placed at its station once it is released
+            if new_package.lock().unwrap().get_release() > 0 {
+                node.lock().unwrap().set_critical();
+                self.pending.push(new_package.clone());
+            } else {
+                node.lock()
+                    .unwrap()
+                    .add_pick_up_package(new_package.clone());
+            }
             let node = new_package.lock().unwrap().get_end_node();
             node.lock()
                 .unwrap()
                 .add_drop_off_package(new_package.lock().unwrap().get_name());
         }
         self.package = packages;
+        self.pending.sort_by_key(|p| {
+            let p = p.lock().unwrap();
+            (p.get_release(), p.get_name())
+        });
+    }
+
+    /// Function to register a package ordered while the simulation runs, it is released straight away
+    /// The shortest paths are calculated again as its stations may be new targets for the trains
+    pub fn add_package(&mut self, mut package: Package) {
+        package.set_release(self.time);
+        let name = package.get_name();
+        let p = Arc::new(Mutex::new(package));
+        self.package.insert(name.clone(), p.clone());
+        let start = p.lock().unwrap().get_start_node();
+        start.lock().unwrap().set_critical();
+        let end = p.lock().unwrap().get_end_node();
+        end.lock().unwrap().add_drop_off_package(name);
+        // It is due now, so before the packages which are released later
+        self.pending.insert(0, p.clone());
+        self.recalculate_shortest_path();
+        self.plan_itinerary(vec![p]);
+    }
+
+    /// Function to let a train take new work or not
+    pub fn set_train_available(&self, name: String, available: bool) {
+        self.get_train(name)
+            .lock()
+            .unwrap()
+            .set_available(available);
+    }
+
+    /// Function to let every train choose its target again at the next node it reaches
+    /// so that work which has not started yet goes to the best train, and to wake up the idle trains
+    pub fn replan(&self) {
+        for t in self.train.values() {
+            let mut t = t.lock().unwrap();
+            t.wake_up();
+            t.set_replan();
+        }
+        self.add_replan_path();
+    }
+
+    /// Function to give the next node of every train which chooses its target again the shortest paths
+    /// to the critical nodes, if it is not a critical node itself
+    /// The paths come from the Dijkstra's Algorithm already run from every critical node
+    fn add_replan_path(&self) {
+        for t in self.train.values() {
+            let (next, class) = {
+                let t = t.lock().unwrap();
+                if !t.is_replanning() {
+                    continue;
+                }
+                (t.get_next_node(), t.get_class())
+            };
+            let next_name = next.lock().unwrap().get_name();
+            if next.lock().unwrap().is_critical()
+                || !next.lock().unwrap().get_shortest_path(&class).is_empty()
+            {
+                continue;
+            }
+            for dest in self.node.values() {
+                if dest.lock().unwrap().get_name() == next_name {
+                    continue;
+                }
+                let shortest_path = dest.lock().unwrap().get_shortest_path_here(&class);
+                if let Some((dist, prev)) = shortest_path {
+                    if let Some(distance) = dist.get(&next_name) {
+                        next.lock().unwrap().add_shortest_path(
+                            &class,
+                            *distance,
+                            dest.clone(),
+                            prev,
+                        );
+                    }
+                }
+            }
+            next.lock().unwrap().sort_shortest_dest();
+        }
+    }
+
+    /// Function to place the packages whose release time has come at their station
+    /// Returns whether any package was released, which is new work for the trains
+    fn release_package(&mut self) -> bool {
+        let mut released = false;
+        while let Some(p) = self.pending.first() {
+            if p.lock().unwrap().get_release() > self.time {
+                break;
+            }
+            let p = self.pending.remove(0);
+            let node = p.lock().unwrap().get_start_node();
+            node.lock().unwrap().add_pick_up_package(p);
+            released = true;
+        }
+        released
     }
 
     pub fn get_package(&self, name: String) -> Arc<Mutex<Package>> {
         self.package.get(&name).unwrap().clone()
     }
 
+    pub fn has_package(&self, name: &str) -> bool {
+        self.package.contains_key(name)
+    }
+
     /// Normal Dijkstra's Shortest Path Algorithm
     /// Implemented using Fibonacci Heap for faster calculation
-    pub fn dijkstra(
-        &self,
-        source: Arc<Mutex<Node>>,
-    ) -> (HashMap<String, i64>, HashMap<String, Option<String>>) {
+    /// The travel time and the edges allowed depend on the class of the train
+    pub fn dijkstra(&self, source: Arc<Mutex<Node>>, class: &TrainClass) -> ShortestPath {
         let mut distance = HashMap::new();
         let mut prev: HashMap<String, Option<String>> = HashMap::new();
         let mut visited = HashSet::new();
@@ -149,7 +278,7 @@ impl Network {
 
             visited.insert(start_node_name.clone());
 
-            for neighbour in start_node.lock().unwrap().get_neighbours() {
+            for neighbour in start_node.lock().unwrap().get_neighbours(class) {
                 // The node is unreachable from other part of graph
                 if *distance.get(&start_node_name).unwrap() == i64::MAX {
                     continue;
@@ -180,40 +309,58 @@ impl Network {
         //     distance.remove(&d.0);
         // }
 
-        return (distance, prev);
+        (distance, prev)
     }
 
     /// Function to calculate all the shortest path between initial train location, package pick up point and drop off point
-    /// Using the Dijkstra's Algorithm above
+    /// Using the Dijkstra's Algorithm above, once for every class of train in the network
     pub fn calculate_shortest_distance_between_packages(&self) {
-        for p in self.package.values() {
-            let source = p.lock().unwrap().get_end_node();
-            let source_name = source.lock().unwrap().get_name();
-
-            let (dist, prev) = self.dijkstra(source.clone());
-
-            for (node, distance) in dist.iter() {
-                let node = self.get_node(node.to_string());
-                let node_name = node.lock().unwrap().get_name();
-                if node.lock().unwrap().is_critical() && node_name != source_name {
-                    node.lock()
-                        .unwrap()
-                        .add_shortest_path(*distance, source.clone(), prev.clone());
-                }
-            }
+        self.calculate_shortest_path();
+        self.plan_itinerary(self.package.values().cloned().collect());
+    }
 
-            let source = p.lock().unwrap().get_start_node();
-            let source_name = source.lock().unwrap().get_name();
+    /// Function to forget the shortest paths and calculate them again, e.g. after a disruption
+    fn recalculate_shortest_path(&self) {
+        for n in self.node.values() {
+            n.lock().unwrap().clear_shortest_path();
+        }
+        self.calculate_shortest_path();
+        self.add_replan_path();
+    }
 
-            let (dist, prev) = self.dijkstra(source.clone());
+    /// Function to register the shortest paths between all the critical nodes for every class of train
+    fn calculate_shortest_path(&self) {
+        let mut classes: Vec<TrainClass> = vec![];
+        for t in self.train.values() {
+            let class = t.lock().unwrap().get_class();
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+        for class in classes.iter() {
+            for p in self.package.values() {
+                let source = p.lock().unwrap().get_end_node();
+                self.add_shortest_path_from(source, class);
 
-            for (node, distance) in dist.iter() {
-                let node = self.get_node(node.to_string());
-                let node_name = node.lock().unwrap().get_name();
-                if node.lock().unwrap().is_critical() && node_name != source_name {
-                    node.lock()
-                        .unwrap()
-                        .add_shortest_path(*distance, source.clone(), prev.clone());
+                let source = p.lock().unwrap().get_start_node();
+                self.add_shortest_path_from(source, class);
+            }
+            // Trains which have to return to a depot or refuel need the way there from every critical node
+            let mut extra_names = HashSet::new();
+            for t in self.train.values() {
+                if let Some(home) = t.lock().unwrap().get_home() {
+                    if extra_names.insert(home.lock().unwrap().get_name()) {
+                        self.add_shortest_path_from(home, class);
+                    }
+                }
+            }
+            // Packages can be handed over at hubs so trains need the way to every hub
+            for n in self.node.values() {
+                let name = n.lock().unwrap().get_name();
+                let extra =
+                    n.lock().unwrap().get_refuel_time().is_some() || n.lock().unwrap().is_hub();
+                if extra && extra_names.insert(name) {
+                    self.add_shortest_path_from(n.clone(), class);
                 }
             }
         }
@@ -222,53 +369,822 @@ impl Network {
         }
     }
 
+    /// Function to apply the disruptions scheduled for the current time, i.e. lines closing or opening
+    /// and journey times changing
+    /// The shortest paths are calculated again and every train follows the new shortest path to its target,
+    /// which may be to wait for a closed line to open
+    fn apply_disruption(&self) {
+        let edges = self.edge.values().cloned().collect::<Vec<_>>();
+        if !edges.iter().any(|e| e.lock().unwrap().has_event(self.time)) {
+            return;
+        }
+        let before = self
+            .train
+            .iter()
+            .map(|(name, t)| (name.clone(), t.lock().unwrap().get_route_time()))
+            .collect::<HashMap<String, i64>>();
+        for e in edges {
+            e.lock().unwrap().update(self.time);
+        }
+        self.recalculate_shortest_path();
+        let after = self.distance_to_target();
+        for (name, (distance, path)) in after {
+            let before = before[&name];
+            self.get_train(name)
+                .lock()
+                .unwrap()
+                .reroute(before, &distance, path);
+        }
+    }
+
+    /// Function to find the travel time from every node to the target of every train which has one
+    fn distance_to_target(&self) -> HashMap<String, ShortestPath> {
+        let mut ret = HashMap::new();
+        for t in self.train.values() {
+            let t = t.lock().unwrap();
+            if let Some(target) = t.get_target() {
+                ret.insert(t.get_name(), self.dijkstra(target, &t.get_class()));
+            }
+        }
+        ret
+    }
+
+    /// Function to plan the hubs every package is handed over at
+    /// Dijkstra's Algorithm on a graph of the pick up point, the hubs and the drop off point,
+    /// where a leg between 2 of them is as long as the fastest compatible class of train can travel it
+    /// A package is only handed over if it gets to its destination sooner than on a single train,
+    /// e.g. when no single class of train can travel the whole way
+    fn plan_itinerary(&self, package: Vec<Arc<Mutex<Package>>>) {
+        let mut hub_names = vec![];
+        for n in self.node.values() {
+            if n.lock().unwrap().is_hub() {
+                hub_names.push(n.lock().unwrap().get_name());
+            }
+        }
+        if hub_names.is_empty() {
+            return;
+        }
+        hub_names.sort();
+        for p in package.iter() {
+            let mut package = p.lock().unwrap();
+            // Classes of the trains which are allowed to and have room to carry this package
+            let mut classes: Vec<TrainClass> = vec![];
+            for t in self.train.values() {
+                let t = t.lock().unwrap();
+                if package.is_compatible(&t.get_tags())
+                    && Dimensions::new().fits(&package.get_smallest_part_size(), &t.get_capacity())
+                    && !classes.contains(&t.get_class())
+                {
+                    classes.push(t.get_class());
+                }
+            }
+            let start_name = package.get_start_node().lock().unwrap().get_name();
+            let end_name = package.get_end_node().lock().unwrap().get_name();
+            let mut stop = vec![start_name.clone()];
+            stop.extend(
+                hub_names
+                    .iter()
+                    .filter(|h| **h != start_name && **h != end_name)
+                    .cloned(),
+            );
+            stop.push(end_name.clone());
+            let leg_distance = |from: &String, to: &String| {
+                let from = self.get_node(from.clone());
+                let from = from.lock().unwrap();
+                classes.iter().filter_map(|c| from.distance_to(c, to)).min()
+            };
+
+            // Distance and number of legs to every stop, fewer legs win on the same distance
+            let mut best: HashMap<String, (i64, usize)> = HashMap::new();
+            let mut prev: HashMap<String, String> = HashMap::new();
+            let mut visited = HashSet::new();
+            best.insert(start_name.clone(), (0, 0));
+            loop {
+                let current = stop
+                    .iter()
+                    .filter(|s| !visited.contains(*s) && best.contains_key(*s))
+                    .min_by_key(|s| best[*s])
+                    .cloned();
+                let current = match current {
+                    Some(c) => c,
+                    None => break,
+                };
+                visited.insert(current.clone());
+                if current == end_name {
+                    break;
+                }
+                for next in stop.iter() {
+                    if visited.contains(next) {
+                        continue;
+                    }
+                    if let Some(d) = leg_distance(&current, next) {
+                        let candidate = (best[&current].0 + d, best[&current].1 + 1);
+                        if best.get(next).is_none_or(|b| candidate < *b) {
+                            best.insert(next.clone(), candidate);
+                            prev.insert(next.clone(), current.clone());
+                        }
+                    }
+                }
+            }
+
+            // Follow the legs back from the destination to get the hubs in between
+            let mut hub = vec![];
+            let mut current = end_name.clone();
+            while let Some(p) = prev.get(&current) {
+                if *p != start_name {
+                    hub.push(self.get_node(p.clone()));
+                }
+                current = p.clone();
+            }
+            hub.reverse();
+            for h in hub.iter() {
+                h.lock().unwrap().add_drop_off_package(package.get_name());
+            }
+            package.set_hub(hub);
+        }
+    }
+
+    /// Function to register the shortest path from every critical node to the source node
+    fn add_shortest_path_from(&self, source: Arc<Mutex<Node>>, class: &TrainClass) {
+        let source_name = source.lock().unwrap().get_name();
+
+        let (dist, prev) = self.dijkstra(source.clone(), class);
+        source
+            .lock()
+            .unwrap()
+            .set_shortest_path_here(class, (dist.clone(), prev.clone()));
+
+        for (node, distance) in dist.iter() {
+            let node = self.get_node(node.to_string());
+            let node_name = node.lock().unwrap().get_name();
+            if node.lock().unwrap().is_critical() && node_name != source_name {
+                node.lock().unwrap().add_shortest_path(
+                    class,
+                    *distance,
+                    source.clone(),
+                    prev.clone(),
+                );
+            }
+        }
+    }
+
+    /// Function to find the packages which cannot be delivered by any train in this network
+    /// and the trains which cannot reach their depot
+    /// Returns a message for every such package and train so that the infeasibility can be reported before simulating
+    pub fn diagnose(&self) -> Vec<String> {
+        let mut package_names = self.package.keys().cloned().collect::<Vec<String>>();
+        package_names.sort();
+        let mut ret = vec![];
+        if self.train.is_empty() {
+            if !self.has_all_package_delivered() {
+                ret.push("There are no trains to deliver the packages".to_string());
+            }
+            return ret;
+        }
+        for name in package_names {
+            let package = self.get_package(name.clone());
+            let package = package.lock().unwrap();
+            if package.is_delivered() {
+                continue;
+            }
+            let compatible = self
+                .train
+                .values()
+                .filter(|t| package.is_compatible(&t.lock().unwrap().get_tags()))
+                .map(|t| t.lock().unwrap().get_capacity())
+                .collect::<Vec<_>>();
+            if compatible.is_empty() {
+                let mut requirement = package.get_requirement().into_iter().collect::<Vec<_>>();
+                requirement.sort();
+                if requirement.is_empty() {
+                    ret.push(format!("Package {} cannot be carried by any train", name));
+                } else {
+                    ret.push(format!(
+                        "Package {} requires {:?} but no train has these tags",
+                        name, requirement
+                    ));
+                }
+            } else if !compatible
+                .iter()
+                .any(|c| Dimensions::new().fits(&package.get_smallest_part_size(), c))
+            {
+                ret.push(format!(
+                    "Package {} ({}) does not fit on any compatible train",
+                    name,
+                    package.get_size()
+                ));
+            }
+        }
+        for t in self.sorted_train() {
+            let t = t.lock().unwrap();
+            if let Some(home) = t.get_home() {
+                let start = t.get_next_node().lock().unwrap().get_name();
+                let (distance, _) = self.dijkstra(home.clone(), &t.get_class());
+                if distance.get(&start).is_none_or(|d| *d == i64::MAX) {
+                    ret.push(format!(
+                        "Train {} cannot reach its home {}",
+                        t.get_name(),
+                        home.lock().unwrap().get_name()
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
     /// Function to check whether all package has been delivered to the destination
     /// Used to stop the simulation once all of them has been delivered
     fn has_all_package_delivered(&self) -> bool {
         for p in self.package.values() {
-            if !p.lock().unwrap().get_arrived() {
+            if !p.lock().unwrap().is_delivered() {
                 return false;
             }
         }
-        return true;
+        true
+    }
+
+    /// Function to get all the nodes ordered by name
+    pub fn sorted_node(&self) -> Vec<Arc<Mutex<Node>>> {
+        let mut node_names = self.node.keys().cloned().collect::<Vec<String>>();
+        node_names.sort();
+        node_names.into_iter().map(|n| self.get_node(n)).collect()
+    }
+
+    /// Function to get all the edges ordered by name, every edge comes before its reverse direction
+    pub fn sorted_edge(&self) -> Vec<Arc<Mutex<Edge>>> {
+        let mut edge_names = self.edge.keys().cloned().collect::<Vec<String>>();
+        edge_names.sort();
+        edge_names.into_iter().map(|e| self.get_edge(e)).collect()
+    }
+
+    /// Function to get all the trains ordered by name
+    /// Trains compete for packages, tracks and platforms so they are always moved in the same order
+    pub fn sorted_train(&self) -> Vec<Arc<Mutex<Train>>> {
+        let mut train_names = self.train.keys().cloned().collect::<Vec<String>>();
+        train_names.sort();
+        train_names.into_iter().map(|t| self.get_train(t)).collect()
     }
 
     /// Main function to simulate the project
     /// The simulation is broken into 1 unit of time for each loop
     /// All the train will be finding the shortest critical nodes if it is on another critical node
-    pub fn simulate(&mut self) {
-        while !self.has_all_package_delivered() {
-            let mut all_train_stopped = true;
+    /// Returns an error if some packages cannot be delivered or the trains are in a deadlock
+    pub fn simulate(&mut self) -> Result<(), String> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// Function to simulate like `simulate`, giving up after this number of steps
+    pub fn simulate_steps(&mut self, steps: u64) -> Result<(), String> {
+        for _ in 0..steps {
+            if !self.step()? {
+                return Ok(());
+            }
+        }
+        Err(format!(
+            "The simulation did not finish within {} steps",
+            steps
+        ))
+    }
+
+    /// Function to simulate 1 unit of time
+    /// Returns whether there is anything left to simulate, or an error if some packages cannot be delivered
+    /// or the trains are in a deadlock
+    pub fn step(&mut self) -> Result<bool, String> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        self.apply_disruption();
+        for n in self.node.values() {
+            n.lock().unwrap().clear_visit();
+        }
+        // New packages are new work for the trains which have ended their trip
+        if self.release_package() {
             for t in self.train.values() {
-                let end = t.lock().unwrap().is_not_end();
-                if !end {
-                    t.lock().unwrap().deliver(self.time);
-                    all_train_stopped = false;
+                t.lock().unwrap().wake_up();
+            }
+        }
+        let mut all_train_stopped = true;
+        let mut blocked_train = HashMap::new();
+        let mut handed_over = false;
+        for t in self.sorted_train() {
+            let end = t.lock().unwrap().is_not_end();
+            if !end {
+                let before = t.lock().unwrap().get_node_name();
+                t.lock().unwrap().deliver(self.time);
+                self.update_train_here(t.clone(), before);
+                all_train_stopped = false;
+                let blocked_by = t.lock().unwrap().get_blocked_by();
+                if let Some(by) = blocked_by {
+                    blocked_train.insert(t.lock().unwrap().get_name(), by);
                 }
+                handed_over |= t.lock().unwrap().has_handed_over();
+            }
+        }
+        // A package left at a hub is new work for the trains which have ended their trip
+        if handed_over {
+            for t in self.train.values() {
+                t.lock().unwrap().wake_up();
+            }
+        }
+        self.time += 1;
+        // println!("{:#?}", self);
+        // Skip to the time the next package is released if every train is idle,
+        // but not past a disruption which has to be applied first
+        if all_train_stopped && !self.pending.is_empty() {
+            let release = self.pending[0].lock().unwrap().get_release();
+            let skip = self.next_disruption().map_or(release, |t| t.min(release));
+            self.time = self.time.max(skip);
+            return Ok(true);
+        }
+        if all_train_stopped {
+            if !self.has_all_package_delivered() {
+                return Err("Some packages are not reachable".to_string());
             }
+            return Ok(false);
+        }
+        // Nothing can change anymore for trains which are only waiting for each other
+        let deadlock = Self::find_deadlock(&blocked_train);
+        if !deadlock.is_empty() {
+            return Err(format!(
+                "Deadlock at time {}, trains {} are waiting for each other",
+                self.time - 1,
+                deadlock.join(", ")
+            ));
+        }
+        Ok(!self.is_finished())
+    }
+
+    /// Function to check whether every package is delivered and every train is back at its depot
+    pub fn is_finished(&self) -> bool {
+        self.has_all_package_delivered() && !self.has_train_returning()
+    }
+
+    pub fn get_time(&self) -> u64 {
+        self.time
+    }
+
+    /// Function to check whether every train has ended its trip and no package is waiting for its release
+    /// Nothing happens until new work comes in
+    pub fn is_idle(&self) -> bool {
+        self.count_active_train() == 0 && self.pending.is_empty()
+    }
+
+    /// Function to let the clock run while the network is idle
+    /// The disruptions scheduled on the way are still applied
+    pub fn wait_until(&mut self, time: u64) {
+        while let Some(t) = self.next_disruption().filter(|t| *t < time) {
+            self.time = t;
+            self.apply_disruption();
             self.time += 1;
-            // println!("{:#?}", self);
-            if all_train_stopped {
-                if !self.has_all_package_delivered() {
-                    panic!("Some packages are not reachable");
-                }
+        }
+        self.time = self.time.max(time);
+    }
+
+    /// Function to find the first time from now on a disruption is scheduled on any edge
+    fn next_disruption(&self) -> Option<u64> {
+        self.edge
+            .values()
+            .filter_map(|e| e.lock().unwrap().next_event(self.time))
+            .min()
+    }
+
+    /// Function to find the trains which wait for each other and can never move again
+    /// `blocked` holds the trains which could not move, with the trains holding what they waited for
+    /// A train is stuck if every train it waits for is stuck too, any other train frees the platform or line
+    /// at some point
+    fn find_deadlock(blocked: &HashMap<String, Vec<String>>) -> Vec<String> {
+        let mut stuck = blocked.keys().cloned().collect::<HashSet<String>>();
+        loop {
+            let next = stuck
+                .iter()
+                .filter(|t| blocked[*t].iter().all(|by| stuck.contains(by)))
+                .cloned()
+                .collect::<HashSet<String>>();
+            if next.len() == stuck.len() {
                 break;
             }
+            stuck = next;
+        }
+        let mut ret = stuck.into_iter().collect::<Vec<String>>();
+        ret.sort();
+        ret
+    }
+
+    /// Function to keep track of which trains are at a node after a train moved
+    /// Used to limit the number of trains at a station with limited platforms
+    /// A train which has ended its trip gives up its platform
+    fn update_train_here(&self, train: Arc<Mutex<Train>>, before: Option<String>) {
+        let end = train.lock().unwrap().is_not_end();
+        let after = match end {
+            true => None,
+            false => train.lock().unwrap().get_node_name(),
+        };
+        let name = train.lock().unwrap().get_name();
+        if let Some(n) = before {
+            self.get_node(n).lock().unwrap().leave_train(&name);
+        }
+        if let Some(n) = after {
+            self.get_node(n).lock().unwrap().arrive_train(train);
+        }
+    }
+
+    /// Function to check whether a train still has to go back to its depot
+    /// The simulation continues after the last delivery until every such train is home
+    fn has_train_returning(&self) -> bool {
+        self.train.values().any(|t| {
+            let t = t.lock().unwrap();
+            !t.is_not_end() && t.get_home().is_some()
+        })
+    }
+
+    /// Function to count the trains which have not ended their trip
+    fn count_active_train(&self) -> usize {
+        self.train
+            .values()
+            .filter(|t| !t.lock().unwrap().is_not_end())
+            .count()
+    }
+
+    /// Function to report the problems found after the simulation
+    /// Lists the rules broken by every train, the trains which left work undone because of their shift
+    /// or did not get back to their depot and the packages which were not delivered
+    pub fn result_report(&self) -> Vec<String> {
+        let mut ret = vec![];
+        for t in self.sorted_train() {
+            let t = t.lock().unwrap();
+            for v in t.get_violation() {
+                ret.push(format!("Train {} {}", t.get_name(), v));
+            }
+            if t.get_delay() > 0 {
+                ret.push(format!(
+                    "Train {} was delayed by {} by disruptions",
+                    t.get_name(),
+                    t.get_delay()
+                ));
+            } else if t.get_delay() < 0 {
+                ret.push(format!(
+                    "Train {} gained {} from shorter journey times",
+                    t.get_name(),
+                    -t.get_delay()
+                ));
+            }
+            if t.is_shift_limited() {
+                ret.push(format!(
+                    "Train {} skipped work which did not fit in its shift after {} on duty",
+                    t.get_name(),
+                    t.get_duty()
+                ));
+            }
+            if let Some(home) = t.get_home() {
+                let home_name = home.lock().unwrap().get_name();
+                if t.get_node_name() != Some(home_name.clone()) {
+                    ret.push(format!(
+                        "Train {} did not get back to its home {}",
+                        t.get_name(),
+                        home_name
+                    ));
+                }
+            }
+        }
+        let mut package_names = self.package.keys().cloned().collect::<Vec<String>>();
+        package_names.sort();
+        for name in package_names {
+            let package = self.get_package(name.clone());
+            let package = package.lock().unwrap();
+            if package.get_delivered() > 0 && !package.is_delivered() {
+                ret.push(format!(
+                    "Package {} was partly delivered, {} of {}",
+                    name,
+                    package.get_delivered(),
+                    package.get_total()
+                ));
+            } else if !package.is_delivered() {
+                ret.push(format!("Package {} was not delivered", name));
+            } else if package.is_late() {
+                ret.push(format!(
+                    "Package {} missed its deadline {}, delivered at {}",
+                    name,
+                    package.get_deadline().unwrap(),
+                    package.get_delivered_at().unwrap()
+                ));
+            }
+        }
+        ret
+    }
+
+    /// Function to draw random delays on the edges with varying journey times, starting from this seed
+    /// Without a seed the journey times do not vary
+    pub fn set_seed(&self, seed: u64) {
+        let random = Arc::new(Mutex::new(Random::new(seed)));
+        for e in self.edge.values() {
+            e.lock().unwrap().set_random(random.clone());
+        }
+    }
+
+    /// Function to describe the state of the simulation as the lines of a snapshot
+    /// The network itself is not part of it, it is created from the input again
+    pub fn save_state(&self) -> Vec<String> {
+        let mut ret = vec![format!("time,{}", self.time)];
+        let mut edge_names = self.edge.keys().cloned().collect::<Vec<String>>();
+        edge_names.sort();
+        for name in edge_names {
+            let state = self.get_edge(name.clone()).lock().unwrap().save_state();
+            ret.push(format!("edge,{},{}", name, state));
+        }
+        // Packages are either waiting at a station or on a train, parts split off included
+        let mut package = self.package.clone();
+        let mut location = HashMap::new();
+        let mut node_names = self.node.keys().cloned().collect::<Vec<String>>();
+        node_names.sort();
+        for n in node_names {
+            for p in self.get_node(n.clone()).lock().unwrap().get_package() {
+                let name = p.lock().unwrap().get_name();
+                location.insert(name.clone(), format!("at={},on=", n));
+                package.insert(name, p);
+            }
+        }
+        for t in self.sorted_train() {
+            let t = t.lock().unwrap();
+            for p in t.get_package() {
+                let name = p.lock().unwrap().get_name();
+                location.insert(name.clone(), format!("at=,on={}", t.get_name()));
+                package.insert(name, p);
+            }
+        }
+        // A part can only be restored after the package it was split off from
+        let mut parent = package.values().cloned().collect::<Vec<_>>();
+        while let Some(p) = parent.pop() {
+            if let Some(p) = p.lock().unwrap().get_parent() {
+                let name = p.lock().unwrap().get_name();
+                parent.push(p.clone());
+                package.insert(name, p);
+            }
+        }
+        let mut package_names = package.keys().cloned().collect::<Vec<String>>();
+        package_names.sort_by_key(|n| (n.matches('/').count(), n.clone()));
+        for name in package_names {
+            let p = package[&name].lock().unwrap();
+            ret.push(format!(
+                "package,{},parent={},{},{}",
+                name,
+                p.get_parent()
+                    .map_or("".to_string(), |x| x.lock().unwrap().get_name()),
+                p.save_state(),
+                location
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or("at=,on=".to_string())
+            ));
+        }
+        for t in self.sorted_train() {
+            let t = t.lock().unwrap();
+            ret.push(format!("train,{},{}", t.get_name(), t.save_state()));
+            for h in t.get_history() {
+                ret.push(format!("history,{},{}", t.get_name(), h.save_state()));
+            }
+            for v in t.get_violation() {
+                ret.push(format!("violation,{},{}", t.get_name(), v));
+            }
+        }
+        ret
+    }
+
+    /// Function to continue the simulation from the state saved by `save_state`
+    /// The network has to be created from the same input and its shortest paths calculated first
+    /// Returns the reason a line of the snapshot is not valid, naming the line
+    pub fn restore_state(&mut self, lines: &[String]) -> Result<(), String> {
+        let mut package = self.package.clone();
+        let mut history: HashMap<String, Vec<History>> = HashMap::new();
+        let mut violation: HashMap<String, Vec<String>> = HashMap::new();
+        for n in self.node.values() {
+            n.lock().unwrap().clear_package();
+        }
+        for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+            let field = line
+                .split(',')
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>();
+            self.restore_line(&field, &mut package, &mut history, &mut violation)
+                .map_err(|e| format!("Snapshot line {}: {}", line, e))?;
+        }
+        for t in self.train.values() {
+            let mut t = t.lock().unwrap();
+            let name = t.get_name();
+            t.set_history(history.remove(&name).unwrap_or_default());
+            t.set_violation(violation.remove(&name).unwrap_or_default());
+        }
+        // The packages released before the snapshot are already at their station
+        let time = self.time;
+        self.pending
+            .retain(|p| p.lock().unwrap().get_release() >= time);
+        // The journey times may have changed since the shortest paths were calculated
+        self.recalculate_shortest_path();
+        Ok(())
+    }
+
+    /// Function to restore the state saved in a line of a snapshot
+    /// `package` holds the packages restored so far, including the parts split off
+    fn restore_line(
+        &mut self,
+        field: &[String],
+        package: &mut HashMap<String, Arc<Mutex<Package>>>,
+        history: &mut HashMap<String, Vec<History>>,
+        violation: &mut HashMap<String, Vec<String>>,
+    ) -> Result<(), String> {
+        if field.len() < 2 {
+            return Err("expected kind,name".to_string());
+        }
+        let name = field[1].clone();
+        let options = parse_options(&field[2..]);
+        match field[0].as_str() {
+            "time" => {
+                self.time = name
+                    .parse()
+                    .map_err(|_| format!("time is not valid: {}", name))?
+            }
+            "edge" => {
+                if !self.edge.contains_key(&name) {
+                    return Err(format!("{} is not a known edge", name));
+                }
+                self.get_edge(name)
+                    .lock()
+                    .unwrap()
+                    .restore_state(&options)?;
+            }
+            "package" => {
+                let p = match package.get(&name) {
+                    Some(p) => p.clone(),
+                    None => {
+                        let parent = options
+                            .get("parent")
+                            .and_then(|x| package.get(x))
+                            .ok_or_else(|| format!("{} is not a known package", name))?
+                            .clone();
+                        let part = parent
+                            .lock()
+                            .unwrap()
+                            .new_part(name.clone(), parent.clone());
+                        Arc::new(Mutex::new(part))
+                    }
+                };
+                p.lock().unwrap().restore_state(&options)?;
+                package.insert(name, p.clone());
+                let at = parse_field::<String>(&options, "at")?;
+                if !at.is_empty() {
+                    self.check_node("at", &at)?;
+                    self.get_node(at).lock().unwrap().add_pick_up_package(p);
+                }
+            }
+            "train" => {
+                if !self.has_train(&name) {
+                    return Err(format!("{} is not a known train", name));
+                }
+                let t = self.get_train(name.clone());
+                let before = t.lock().unwrap().get_node_name();
+                let at = parse_field::<String>(&options, "location")?;
+                let location = match parse_field::<bool>(&options, "on_edge")? {
+                    true => {
+                        if !self.edge.contains_key(&at) {
+                            return Err(format!("location is not a known edge: {}", at));
+                        }
+                        let e = self.get_edge(at);
+                        e.lock().unwrap().enter(name.clone());
+                        Location::Edge(e)
+                    }
+                    false => {
+                        self.check_node("location", &at)?;
+                        Location::Node(self.get_node(at))
+                    }
+                };
+                let class = t.lock().unwrap().get_class();
+                let route = match parse_field::<String>(&options, "target")?.as_str() {
+                    "" => (None, HashMap::new()),
+                    target => {
+                        self.check_node("target", target)?;
+                        let target = self.get_node(target.to_string());
+                        let (_, path) = self.dijkstra(target.clone(), &class);
+                        (Some(target), path)
+                    }
+                };
+                let mut load = vec![];
+                for n in parse_sequence(options.get("package")) {
+                    match package.get(&n) {
+                        Some(p) => load.push(p.clone()),
+                        None => return Err(format!("package is not a known package: {}", n)),
+                    }
+                }
+                let mut train = t.lock().unwrap();
+                train.restore_state(&options)?;
+                train.set_location(location);
+                train.set_route(route.0, route.1);
+                train.set_package(load);
+                drop(train);
+                self.update_train_here(t, before);
+            }
+            "history" => history
+                .entry(name.clone())
+                .or_default()
+                .push(History::restore_state(name, &options)?),
+            "violation" => violation
+                .entry(name)
+                .or_default()
+                .push(field[2..].join(",")),
+            _ => return Err(format!("{} is not a kind of line", field[0])),
+        }
+        Ok(())
+    }
+
+    /// Function to check that a field of a snapshot names a station of this network
+    fn check_node(&self, key: &str, name: &str) -> Result<(), String> {
+        match self.has_node(name) {
+            true => Ok(()),
+            false => Err(format!("{} is not a known station: {}", key, name)),
+        }
+    }
+
+    /// Function to get the time the last train arrived at a station
+    pub fn get_makespan(&self) -> u64 {
+        self.train
+            .values()
+            .flat_map(|t| t.lock().unwrap().get_history())
+            .map(|h| h.get_arrival())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Function to get every package ordered by name
+    pub fn sorted_package(&self) -> Vec<Arc<Mutex<Package>>> {
+        let mut package_names = self.package.keys().cloned().collect::<Vec<String>>();
+        package_names.sort();
+        package_names
+            .into_iter()
+            .map(|p| self.get_package(p))
+            .collect()
+    }
+
+    /// Function to check the simulation history against the loading rules of the trains
+    /// A train with LIFO loading may only drop off the package it loaded last
+    /// Returns a message for every drop off which breaks the rule
+    pub fn validate_plan(&self) -> Vec<String> {
+        let mut ret = vec![];
+        for t in self.sorted_train() {
+            let t = t.lock().unwrap();
+            if !t.is_lifo() {
+                continue;
+            }
+            let mut stack: Vec<String> = vec![];
+            for h in t.get_history() {
+                stack.extend(h.get_picked_up());
+                for p in h.get_dropped_off() {
+                    if stack.last() != Some(&p) {
+                        ret.push(format!(
+                            "Train {} drops off {} which is not on the top of its stack {:?}",
+                            h.get_train(),
+                            p,
+                            stack
+                        ));
+                    }
+                    stack.retain(|x| *x != p);
+                }
+            }
         }
+        ret
     }
 
     /// Function to output the simulation history as shown in the assignment description
     pub fn print_history(&self) {
+        for h in self.get_history() {
+            println!("{}", h);
+        }
+    }
+
+    /// Function to describe the results of the simulation as JSON: the history, the KPI report and the
+    /// problems listed after the output
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            (
+                "history",
+                Json::Array(self.get_history().iter().map(|h| h.to_json()).collect()),
+            ),
+            ("kpis", Kpi::new(self).to_json()),
+            ("report", self.result_report().into()),
+            ("invalid_plan", self.validate_plan().into()),
+        ])
+    }
+
+    /// Function to get the history of every train ordered by time
+    /// A record the train has not arrived for yet, e.g. of a train still dwelling at the end, is left out
+    pub fn get_history(&self) -> Vec<History> {
         let mut history = self
             .train
             .clone()
             .into_values()
             .flat_map(|x| x.lock().unwrap().get_history())
+            .filter(|h| h.has_arrived())
             .collect::<Vec<History>>();
         history.sort();
-        for h in history {
-            println!("{}", h);
-        }
+        history
     }
 }
 
@@ -294,6 +1210,419 @@ impl PartialEq for DistanceToSource {
 
 impl PartialOrd for DistanceToSource {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_network, create_package};
+
+    /// Function to build a network from the lines of the input
+    fn network(node: &[&str], edge: &[&str], package: &[&str], train: &[&str]) -> Network {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let network = create_network(lines(node), lines(edge), lines(train), lines(package));
+        network.calculate_shortest_distance_between_packages();
+        network
+    }
+
+    fn history(network: &Network) -> Vec<String> {
+        network
+            .get_history()
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn package_is_only_carried_by_a_train_with_its_tags() {
+        let mut network = network(
+            &["A", "B"],
+            &["E1,A,B,5"],
+            &["K1,5,A,B,requires=hazardous"],
+            &["Q1,10,A", "Q2,10,B,tags=hazardous|refrigerated"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q2, N1=B, P1=[], N2=A, P2=[]",
+                "W=5, T=Q2, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn packages_no_train_can_carry_are_diagnosed() {
+        let network = network(
+            &["A", "B"],
+            &["E1,A,B,5"],
+            &[
+                "K1,5,A,B,requires=refrigerated",
+                "K2,20,A,B",
+                "K3,weight:5;slots:2,A,B",
+                "K4,5,A,B",
+                "K5,5,A,B,requires=hazardous",
+            ],
+            &["Q1,weight:10;slots:1,A", "Q2,4,A,tags=refrigerated"],
+        );
+        assert_eq!(
+            network.diagnose(),
+            vec![
+                "Package K1 (weight:5) does not fit on any compatible train",
+                "Package K2 (weight:20) does not fit on any compatible train",
+                "Package K3 (slots:2;weight:5) does not fit on any compatible train",
+                "Package K5 requires [\"hazardous\"] but no train has these tags",
+            ]
+        );
+    }
+
+    #[test]
+    fn departure_includes_the_dwell_and_handling_time() {
+        let mut network = network(
+            &["A,dwell=2,handling=1", "B,dwell=1,handling=3", "C"],
+            &["E1,A,B,5", "E2,B,C,5"],
+            &["K1,5,A,B", "K2,5,B,C"],
+            &["Q1,10,A"],
+        );
+        network.simulate().unwrap();
+        // 1 package is handled at A and 2 at B
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=3, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]",
+                "W=15, T=Q1, N1=B, P1=[\"K2\"], N2=C, P2=[\"K2\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn train_waits_for_a_single_track() {
+        let mut network = network(
+            &["A", "B", "C"],
+            &["E1,A,B,10,capacity=1", "E2,B,C,5"],
+            &["K1,5,A,C", "K2,5,B,A"],
+            &["Q1,6,A", "Q2,6,B"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[]",
+                "W=10, T=Q1, N1=B, P1=[], N2=C, P2=[\"K1\"]",
+                "W=10, T=Q2, N1=B, P1=[\"K2\"], N2=A, P2=[\"K2\"], WAIT=10",
+            ]
+        );
+    }
+
+    #[test]
+    fn train_queues_for_a_platform() {
+        let mut network = network(
+            &["A", "B,platforms=1,dwell=12", "C"],
+            &["E1,A,B,10", "E2,B,C,5"],
+            &["K1,5,A,C", "K2,5,B,C"],
+            &["Q1,6,B", "Q2,6,A"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q2, N1=A, P1=[\"K1\"], N2=B, P2=[], QUEUE=2",
+                "W=12, T=Q1, N1=B, P1=[\"K2\"], N2=C, P2=[\"K2\"]",
+                "W=12, T=Q2, N1=B, P1=[], N2=C, P2=[\"K1\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn trains_waiting_for_each_other_are_a_deadlock() {
+        // Q2 holds the line waiting for the platform Q1 holds while waiting for the line
+        let mut network = network(
+            &["A,platforms=1,dwell=20", "B"],
+            &["E1,A,B,5,capacity=1"],
+            &["K1,5,A,B", "K2,5,B,A"],
+            &["Q1,6,A", "Q2,6,B"],
+        );
+        assert_eq!(
+            network.simulate(),
+            Err("Deadlock at time 20, trains Q1, Q2 are waiting for each other".to_string())
+        );
+    }
+
+    #[test]
+    fn train_which_cannot_reach_its_home_is_reported() {
+        let mut network = network(
+            &["A", "B", "C"],
+            &["E1,A,B,5"],
+            &["K1,5,A,B"],
+            &["Q1,6,A,home=C", "Q2,6,B,home"],
+        );
+        assert_eq!(network.diagnose(), vec!["Train Q1 cannot reach its home C"]);
+        network.simulate().unwrap();
+        assert_eq!(
+            network.result_report(),
+            vec!["Train Q1 did not get back to its home C"]
+        );
+    }
+
+    #[test]
+    fn train_does_not_start_a_trip_the_dwell_time_pushes_past_its_shift() {
+        let train = |shift| network(&["A", "B,dwell=3"], &["E1,A,B,5"], &["K1,5,A,B"], &[shift]);
+        let mut network = train("Q1,6,A,shift=7");
+        let _ = network.simulate();
+        assert!(history(&network).is_empty());
+        assert_eq!(
+            network.result_report(),
+            vec![
+                "Train Q1 skipped work which did not fit in its shift after 0 on duty",
+                "Package K1 was not delivered"
+            ]
+        );
+
+        let mut network = train("Q1,6,A,shift=8");
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec!["W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]"]
+        );
+        assert!(network.result_report().is_empty());
+    }
+
+    #[test]
+    fn rests_on_the_way_count_toward_the_shift() {
+        let train = |shift| {
+            network(
+                &["A", "B", "C"],
+                &["E1,A,B,4", "E2,B,C,4"],
+                &["K1,5,A,C"],
+                &[shift],
+            )
+        };
+        let mut network = train("Q1,6,A,shift=10,rest=5:3");
+        let _ = network.simulate();
+        assert!(history(&network).is_empty());
+        assert_eq!(
+            network.result_report(),
+            vec![
+                "Train Q1 skipped work which did not fit in its shift after 0 on duty",
+                "Package K1 was not delivered"
+            ]
+        );
+
+        let mut network = train("Q1,6,A,shift=11,rest=5:3");
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[]",
+                "W=7, T=Q1, N1=B, P1=[], N2=C, P2=[\"K1\"], REST=3",
+            ]
+        );
+        assert!(network.result_report().is_empty());
+    }
+
+    #[test]
+    fn faster_train_takes_the_line_its_class_is_allowed_on() {
+        let mut network = network(
+            &["A", "B", "C"],
+            &["E1,A,C,10,ban=freight", "E2,A,B,5", "E3,B,C,10"],
+            &["K1,5,A,C", "K2,5,A,C"],
+            &["Q1,5,A,class=freight,speed=2", "Q2,5,A,speed=0.5"],
+        );
+        network.simulate().unwrap();
+        // The journey times are divided by the speed and rounded up
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[]",
+                "W=0, T=Q2, N1=A, P1=[\"K2\"], N2=C, P2=[\"K2\"]",
+                "W=3, T=Q1, N1=B, P1=[], N2=C, P2=[\"K1\"]",
+            ]
+        );
+        assert_eq!(network.get_makespan(), 20);
+    }
+
+    #[test]
+    fn train_refuels_before_taking_a_package() {
+        let mut network = network(
+            &["A", "B,refuel=1", "C,refuel=1", "D,refuel=5"],
+            &["E1,D,A,10", "E2,A,B,10", "E3,A,C,2"],
+            &["K1,5,A,B"],
+            &["Q3,5,D,fuel=40,burn_load=0.5"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q3, N1=D, P1=[], N2=A, P2=[]",
+                "W=10, T=Q3, N1=A, P1=[], N2=C, P2=[]",
+                "W=13, T=Q3, N1=C, P1=[], N2=A, P2=[], REFUEL",
+                "W=15, T=Q3, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn train_does_not_refuel_for_work_a_full_tank_cannot_do() {
+        let mut network = network(
+            &["A,refuel", "B,refuel", "C"],
+            &["E1,A,B,3", "E2,A,C,20"],
+            &["K1,5,A,C"],
+            &["Q1,6,A,fuel=10"],
+        );
+        assert_eq!(
+            network.simulate(),
+            Err("Some packages are not reachable".to_string())
+        );
+        assert!(history(&network).is_empty());
+    }
+
+    #[test]
+    fn package_is_handed_over_at_a_hub() {
+        let mut network = network(
+            &["A", "B", "H,hub", "C"],
+            &["E1,A,H,10,ban=long", "E2,H,C,20,ban=local", "E3,B,A,5"],
+            &["K1,5,A,C"],
+            &["Q1,6,B,class=local", "Q2,6,C,class=long"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=B, P1=[], N2=A, P2=[]",
+                "W=5, T=Q1, N1=A, P1=[\"K1\"], N2=H, P2=[\"K1\"], TRANSFER=[\"K1\"]",
+                "W=16, T=Q2, N1=C, P1=[], N2=H, P2=[]",
+                "W=36, T=Q2, N1=H, P1=[\"K1\"], N2=C, P2=[\"K1\"]",
+            ]
+        );
+        let k1 = network.get_package("K1".to_string());
+        assert!(k1.lock().unwrap().is_delivered());
+    }
+
+    #[test]
+    fn divisible_package_is_split_between_trains() {
+        let mut network = network(
+            &["A", "B", "C"],
+            &["E1,A,B,30", "E2,B,C,10"],
+            &["K1,weight:10;volume:5,A,C,divisible"],
+            &["Q1,6,B", "Q2,weight:20;volume:1,B"],
+        );
+        network.simulate().unwrap();
+        let delivered = history(&network)
+            .into_iter()
+            .filter(|h| h.contains("P2=[\"K1"))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            delivered,
+            vec![
+                "W=60, T=Q1, N1=B, P1=[], N2=C, P2=[\"K1/1\"]",
+                "W=60, T=Q2, N1=B, P1=[], N2=C, P2=[\"K1/2\"]",
+                "W=140, T=Q1, N1=B, P1=[], N2=C, P2=[\"K1\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn lifo_train_only_drops_off_the_package_loaded_last() {
+        let mut network = network(
+            &["A", "B", "C"],
+            &["E1,A,B,5", "E2,B,C,5"],
+            &["K1,5,A,C", "K2,5,B,C", "K3,5,A,B"],
+            &["Q1,20,A,lifo"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\", \"K3\"], N2=B, P2=[\"K3\"]",
+                "W=5, T=Q1, N1=B, P1=[\"K2\"], N2=C, P2=[\"K2\", \"K1\"]",
+            ]
+        );
+        assert!(network.validate_plan().is_empty());
+    }
+
+    #[test]
+    fn released_package_wakes_up_the_idle_train() {
+        let mut network = network(
+            &["A", "B"],
+            &["E1,A,B,5"],
+            &["K1,5,A,B", "K2,5,A,B,release=30"],
+            &["Q1,10,A"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]",
+                "W=30, T=Q1, N1=B, P1=[], N2=A, P2=[]",
+                "W=35, T=Q1, N1=A, P1=[\"K2\"], N2=B, P2=[\"K2\"]",
+            ]
+        );
+    }
+
+    #[test]
+    fn journey_time_change_is_applied_while_every_train_is_idle() {
+        let mut network = network(
+            &["A", "B"],
+            &["E1,A,B,30,journey=10:5"],
+            &["K1,5,A,B,release=20"],
+            &["Q1,6,A"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec!["W=20, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"]"]
+        );
+        assert_eq!(network.get_makespan(), 25);
+    }
+
+    #[test]
+    fn line_closure_is_applied_while_every_train_is_idle() {
+        let mut network = network(
+            &["A", "B"],
+            &["E1,A,B,30,closed=10:100"],
+            &["K1,5,A,B,release=20"],
+            &["Q1,6,A"],
+        );
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec!["W=100, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[\"K1\"], DELAY=80"]
+        );
+        assert_eq!(
+            network.result_report(),
+            vec!["Train Q1 was delayed by 80 by disruptions"]
+        );
+    }
+
+    #[test]
+    fn train_chooses_its_target_again_at_the_next_node_after_replanning() {
+        let mut network = network(
+            &["A", "B", "C", "D"],
+            &["E1,A,B,5", "E2,B,C,5", "E3,B,D,2"],
+            &["K1,5,A,C"],
+            &["Q1,10,A"],
+        );
+        network.step().unwrap();
+        network.step().unwrap();
+        let package = create_package("K2,5,D,C", &network);
+        network.add_package(package);
+        network.replan();
+        assert!(!network
+            .get_node("B".to_string())
+            .lock()
+            .unwrap()
+            .is_critical());
+        network.simulate().unwrap();
+        assert_eq!(
+            history(&network),
+            vec![
+                "W=0, T=Q1, N1=A, P1=[\"K1\"], N2=B, P2=[]",
+                "W=5, T=Q1, N1=B, P1=[], N2=D, P2=[]",
+                "W=7, T=Q1, N1=D, P1=[\"K2\"], N2=B, P2=[]",
+                "W=9, T=Q1, N1=B, P1=[], N2=C, P2=[\"K2\", \"K1\"]",
+            ]
+        );
     }
 }
@@ -3,7 +3,7 @@ use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 
-use crate::train::Train;
+use crate::train::{Train, TrainClass};
 use crate::{edge::Edge, package::Package};
 
 /// The node structure
@@ -14,10 +14,26 @@ pub struct Node {
     pick_up_package: HashMap<String, Arc<Mutex<Package>>>,
     drop_off_package: HashSet<String>,
     train_here: HashMap<String, Arc<Mutex<Train>>>,
-    shortest_path_to_other_critical_nodes: Vec<ShortestPathToCritical>,
+    /// Trains which stopped here in the current unit of time, they hold a platform until its end
+    /// even if they left again at once
+    visit: HashSet<String>,
+    /// The shortest paths for every class of train, keyed by `TrainClass::key`
+    shortest_path_to_other_critical_nodes: HashMap<String, Vec<ShortestPathToCritical>>,
+    /// The shortest paths to this node from every other node for every class of train, if it is a critical node
+    shortest_path_here: HashMap<String, ShortestPath>,
     /// Critical node is defined as whether it is the initial position for a train
     /// or the pick up/drop off point of a package
     critical: bool,
+    /// Fixed time a train stays here whenever it drops off or picks up packages
+    dwell_time: u64,
+    /// Additional time needed for every package dropped off or picked up here
+    handling_time: u64,
+    /// Maximum number of trains which can be at this station at once, unlimited if not set
+    platform: Option<u64>,
+    /// Time needed to refuel a train if this is a refuelling or charging station
+    refuel_time: Option<u64>,
+    /// Whether packages can be handed over between trains here
+    hub: bool,
 }
 
 impl Debug for Node {
@@ -30,15 +46,12 @@ impl Debug for Node {
                     .clone()
                     .into_iter()
                     .map(|x| {
-                        format!(
-                            "{}",
-                            x.1.lock()
-                                .unwrap()
-                                .get_end_node()
-                                .lock()
-                                .unwrap()
-                                .get_name()
-                        )
+                        x.1.lock()
+                            .unwrap()
+                            .get_end_node()
+                            .lock()
+                            .unwrap()
+                            .get_name()
                     })
                     .collect::<Vec<String>>()
                     .join(", "),
@@ -57,6 +70,15 @@ impl Debug for Node {
                     .collect::<Vec<String>>()
                     .join(", "),
             )
+            .field(
+                "Train(s) here",
+                &self
+                    .train_here
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )
             .field(
                 "Drop off point",
                 &self
@@ -92,8 +114,15 @@ impl Node {
             pick_up_package: HashMap::new(),
             drop_off_package: HashSet::new(),
             train_here: HashMap::new(),
-            shortest_path_to_other_critical_nodes: vec![],
+            visit: HashSet::new(),
+            shortest_path_to_other_critical_nodes: HashMap::new(),
+            shortest_path_here: HashMap::new(),
             critical: false,
+            dwell_time: 0,
+            handling_time: 0,
+            platform: None,
+            refuel_time: None,
+            hub: false,
         }
     }
 
@@ -101,12 +130,33 @@ impl Node {
         self.name.clone()
     }
 
+    pub fn set_service_time(&mut self, dwell_time: u64, handling_time: u64) {
+        self.dwell_time = dwell_time;
+        self.handling_time = handling_time;
+    }
+
+    /// Function to calculate how long a train stops here to handle the given number of packages
+    /// A train passing through without handling any package does not stop
+    pub fn get_service_time(&self, package_handled: usize) -> u64 {
+        if package_handled == 0 {
+            return 0;
+        }
+        self.dwell_time + self.handling_time * package_handled as u64
+    }
+
     /// Function to find all the direct neighbour nodes to this node
-    pub fn get_neighbours(&self) -> Vec<(Arc<Mutex<Node>>, i64)> {
+    /// which a train of this class is allowed to travel to, with its travel time
+    /// A closed edge takes the time until it opens on top of the travel time
+    pub fn get_neighbours(&self, class: &TrainClass) -> Vec<(Arc<Mutex<Node>>, i64)> {
         let mut ret = vec![];
         for edge in &self.edge {
-            let end_node = edge.1.lock().unwrap().get_end_node();
-            ret.push((end_node, edge.1.lock().unwrap().get_journey_time()));
+            let edge = edge.1.lock().unwrap();
+            if edge.allows(class) {
+                ret.push((
+                    edge.get_end_node(),
+                    (edge.get_travel_time(class) + edge.get_delay()) as i64,
+                ));
+            }
         }
         ret
     }
@@ -133,6 +183,84 @@ impl Node {
         self.critical = true;
     }
 
+    /// Function to register a train arriving here during the simulation
+    /// Unlike `add_train` this does not make the node a critical node
+    pub fn arrive_train(&mut self, train: Arc<Mutex<Train>>) {
+        self.train_here
+            .insert(train.lock().unwrap().get_name(), train.clone());
+    }
+
+    pub fn leave_train(&mut self, name: &str) {
+        self.train_here.remove(name);
+    }
+
+    pub fn set_platform(&mut self, platform: Option<u64>) {
+        self.platform = platform;
+    }
+
+    /// Function to record that a train stopped here in the current unit of time
+    pub fn visit_train(&mut self, name: String) {
+        self.visit.insert(name);
+    }
+
+    /// Function to start a new unit of time, the trains which only stopped here have left
+    pub fn clear_visit(&mut self) {
+        self.visit = HashSet::new();
+    }
+
+    /// Function to get the names of the trains taking a platform here, including those which only stopped
+    /// in the current unit of time
+    pub fn get_train_on_platform(&self) -> Vec<String> {
+        let mut ret = self
+            .train_here
+            .keys()
+            .chain(self.visit.iter())
+            .cloned()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        ret.sort();
+        ret
+    }
+
+    /// Function to check whether one more train can stop at this station
+    pub fn has_free_platform(&self) -> bool {
+        match self.platform {
+            Some(p) => (self.get_train_on_platform().len() as u64) < p,
+            None => true,
+        }
+    }
+
+    /// Function to make this node a refuelling station
+    /// A refuelling station is a critical node so that trains can find their way to it
+    pub fn set_refuel_time(&mut self, refuel_time: Option<u64>) {
+        self.refuel_time = refuel_time;
+        if refuel_time.is_some() {
+            self.critical = true;
+        }
+    }
+
+    pub fn get_refuel_time(&self) -> Option<u64> {
+        self.refuel_time
+    }
+
+    /// Function to make this node a hub where packages can be handed over between trains
+    pub fn set_hub(&mut self, hub: bool) {
+        self.hub = hub;
+        if hub {
+            self.critical = true;
+        }
+    }
+
+    pub fn is_hub(&self) -> bool {
+        self.hub
+    }
+
+    /// Function to make this node a critical node, e.g. when it is the depot of a train
+    pub fn set_critical(&mut self) {
+        self.critical = true;
+    }
+
     pub fn is_critical(&self) -> bool {
         // !self.pick_up_package.is_empty()
         //     || !self.drop_off_package.is_empty()
@@ -142,11 +270,14 @@ impl Node {
 
     pub fn add_shortest_path(
         &mut self,
+        class: &TrainClass,
         dis: i64,
         dest: Arc<Mutex<Node>>,
         path: HashMap<String, Option<String>>,
     ) {
         self.shortest_path_to_other_critical_nodes
+            .entry(class.key())
+            .or_default()
             .push(ShortestPathToCritical {
                 distance_to_destination: dis,
                 destination: dest,
@@ -154,22 +285,63 @@ impl Node {
             });
     }
 
+    /// Function to forget all the shortest paths so that they can be calculated again
+    pub fn clear_shortest_path(&mut self) {
+        self.shortest_path_to_other_critical_nodes = HashMap::new();
+        self.shortest_path_here = HashMap::new();
+    }
+
+    /// Function to keep the shortest paths to this node found by Dijkstra's Algorithm from here
+    /// so that a node which is not critical can find its way here later
+    pub fn set_shortest_path_here(&mut self, class: &TrainClass, shortest_path: ShortestPath) {
+        self.shortest_path_here.insert(class.key(), shortest_path);
+    }
+
+    pub fn get_shortest_path_here(&self, class: &TrainClass) -> Option<ShortestPath> {
+        self.shortest_path_here.get(&class.key()).cloned()
+    }
+
     /// Function to sort the calculated shortest distance to all other critical nodes
     /// we need this because the train will loop for all shortest path and break earlier once there is a valid one
     pub fn sort_shortest_dest(&mut self) {
-        self.shortest_path_to_other_critical_nodes.sort_unstable();
+        for path in self.shortest_path_to_other_critical_nodes.values_mut() {
+            path.sort_unstable();
+        }
     }
 
-    pub fn get_shortest_path(&self) -> Vec<ShortestPathToCritical> {
-        self.shortest_path_to_other_critical_nodes.clone()
+    pub fn get_shortest_path(&self, class: &TrainClass) -> Vec<ShortestPathToCritical> {
+        self.shortest_path_to_other_critical_nodes
+            .get(&class.key())
+            .cloned()
+            .unwrap_or_default()
     }
 
-    pub fn get_package(&self) -> Vec<Arc<Mutex<Package>>> {
-        let mut ret = vec![];
-        for p in self.pick_up_package.values() {
-            ret.push(p.clone());
+    /// Function to find the shortest travel time from here to another critical node for this class of train
+    /// Returns `None` if the destination cannot be reached
+    pub fn distance_to(&self, class: &TrainClass, dest: &str) -> Option<i64> {
+        if self.name == dest {
+            return Some(0);
         }
-        ret
+        self.get_shortest_path(class)
+            .into_iter()
+            .find(|x| x.destination.lock().unwrap().get_name() == dest)
+            .map(|x| x.distance_to_destination)
+            .filter(|d| *d != i64::MAX)
+    }
+
+    /// Function to get the packages waiting to be picked up here, ordered by name
+    pub fn get_package(&self) -> Vec<Arc<Mutex<Package>>> {
+        let mut names = self.pick_up_package.keys().collect::<Vec<&String>>();
+        names.sort();
+        names
+            .into_iter()
+            .map(|n| self.pick_up_package[n].clone())
+            .collect()
+    }
+
+    /// Function to remove all the packages waiting to be picked up here
+    pub fn clear_package(&mut self) {
+        self.pick_up_package = HashMap::new();
     }
 
     pub fn remove_package(&mut self, package: String) {
@@ -181,14 +353,6 @@ impl Node {
     //     self.pick_up_package.contains_key(&name)
     // }
 
-    /// Function to return a list of packages which will be dropped here
-    pub fn get_drop_off(&self) -> Vec<String> {
-        self.drop_off_package
-            .clone()
-            .into_iter()
-            .collect::<Vec<String>>()
-    }
-
     /// Function to search for an edge based on the next node
     pub fn get_edge(&self, dest: String) -> Arc<Mutex<Edge>> {
         for e in self.edge.values() {
@@ -200,6 +364,9 @@ impl Node {
     }
 }
 
+/// The travel time to the source from every node and the next node on the way, as found by Dijkstra's Algorithm
+pub type ShortestPath = (HashMap<String, i64>, HashMap<String, Option<String>>);
+
 /// A special structure to hold the information between all critical nodes
 #[derive(Clone)]
 pub struct ShortestPathToCritical {
@@ -235,7 +402,15 @@ impl PartialOrd for ShortestPathToCritical {
 
 impl Ord for ShortestPathToCritical {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Nodes at the same distance are ordered by name so that every run makes the same choice
         self.distance_to_destination
             .cmp(&other.distance_to_destination)
+            .then_with(|| {
+                if Arc::ptr_eq(&self.destination, &other.destination) {
+                    return std::cmp::Ordering::Equal;
+                }
+                let name = self.destination.lock().unwrap().get_name();
+                name.cmp(&other.destination.lock().unwrap().get_name())
+            })
     }
 }
@@ -1,17 +1,46 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::Debug,
     sync::{Arc, Mutex},
 };
 
-use crate::node::Node;
+use crate::{
+    dimension::{Dimensions, DEFAULT_DIMENSION},
+    node::Node,
+    parse::{parse_field, parse_optional_field},
+};
 
 /// A package structure
 pub struct Package {
     name: String,
-    weight: u64,
+    /// Size of the package on every dimension a train may be limited by
+    size: Dimensions,
     start: Arc<Mutex<Node>>,
     end: Arc<Mutex<Node>>,
-    arrived: bool,
+    /// Whether the package can be split between trains, by units of weight
+    divisible: bool,
+    /// Units of the package which are still to be handled as this package, i.e. not split off
+    quantity: u64,
+    /// Units of the package when it was created
+    total: u64,
+    /// Units of the package which have reached the destination, including its split off parts
+    delivered: u64,
+    /// The package this one was split off from
+    parent: Option<Arc<Mutex<Package>>>,
+    /// Number of parts split off from this package
+    part: u64,
+    /// Tags a train must have to carry this package, e.g. hazardous or refrigerated
+    requirement: HashSet<String>,
+    /// The hubs the package is handed over at, followed by its destination
+    itinerary: Vec<Arc<Mutex<Node>>>,
+    /// Index of the next stop of the package in the itinerary
+    leg: usize,
+    /// The time the package is ready to be picked up, it is unknown to the trains before that
+    release: u64,
+    /// The time the package has to be delivered by, if any
+    deadline: Option<u64>,
+    /// The time the last unit of the package reached the destination
+    delivered_at: Option<u64>,
 }
 
 impl Debug for Package {
@@ -19,9 +48,14 @@ impl Debug for Package {
         let start_node = self.start.lock().unwrap().get_name();
         let end_node = self.end.lock().unwrap().get_name();
         f.debug_struct(&self.name)
-            .field("Weight", &self.weight)
+            .field("Size", &self.size)
             .field("Start", &start_node)
             .field("Destination", &end_node)
+            .field("Delivered", &format!("{}/{}", self.delivered, self.total))
+            .field("Requirement", &self.requirement)
+            .field("Release", &self.release)
+            .field("Deadline", &self.deadline)
+            .field("Itinerary", &self.get_itinerary())
             .finish()
     }
 }
@@ -33,15 +67,35 @@ impl PartialEq for Package {
 }
 
 impl Package {
-    pub fn new(name: String, weight: u64, start: Arc<Mutex<Node>>, end: Arc<Mutex<Node>>) -> Self {
+    pub fn new(
+        name: String,
+        size: Dimensions,
+        start: Arc<Mutex<Node>>,
+        end: Arc<Mutex<Node>>,
+    ) -> Self {
         let start_name = start.lock().unwrap().get_name();
         let end_name = end.lock().unwrap().get_name();
         Self {
             name,
-            weight,
+            size,
             start,
-            end,
-            arrived: start_name == end_name,
+            end: end.clone(),
+            divisible: false,
+            quantity: 1,
+            total: 1,
+            delivered: if start_name == end_name { 1 } else { 0 },
+            parent: None,
+            part: 0,
+            requirement: HashSet::new(),
+            itinerary: vec![end.clone()],
+            leg: 0,
+            release: 0,
+            deadline: None,
+            delivered_at: if start_name == end_name {
+                Some(0)
+            } else {
+                None
+            },
         }
     }
 
@@ -57,16 +111,205 @@ impl Package {
         self.end.clone()
     }
 
-    pub fn get_weight(&self) -> u64 {
-        self.weight
+    pub fn get_size(&self) -> Dimensions {
+        self.size.clone()
+    }
+
+    /// Function to get the node the package has to be brought to next, a hub or its destination
+    pub fn get_next_stop(&self) -> Arc<Mutex<Node>> {
+        self.itinerary[self.leg].clone()
+    }
+
+    pub fn get_itinerary(&self) -> Vec<String> {
+        self.itinerary
+            .iter()
+            .map(|n| n.lock().unwrap().get_name())
+            .collect()
+    }
+
+    /// Function to set the hubs the package is handed over at, the destination is added at the end
+    pub fn set_hub(&mut self, hub: Vec<Arc<Mutex<Node>>>) {
+        self.itinerary = hub;
+        self.itinerary.push(self.end.clone());
+        self.leg = 0;
+    }
+
+    /// Function to check whether the next stop of the package is its destination
+    pub fn is_last_leg(&self) -> bool {
+        self.leg + 1 == self.itinerary.len()
+    }
+
+    /// Function to hand the package over at a hub, its next stop becomes the following one
+    pub fn transfer(&mut self) {
+        self.leg += 1;
+    }
+
+    pub fn get_requirement(&self) -> HashSet<String> {
+        self.requirement.clone()
+    }
+
+    pub fn set_release(&mut self, release: u64) {
+        self.release = release;
+    }
+
+    pub fn get_release(&self) -> u64 {
+        self.release
+    }
+
+    pub fn set_requirement(&mut self, requirement: HashSet<String>) {
+        self.requirement = requirement;
+    }
+
+    /// Function to check whether a train with these tags is allowed to carry this package
+    pub fn is_compatible(&self, tags: &HashSet<String>) -> bool {
+        self.requirement.is_subset(tags)
+    }
+
+    /// Function to let the package be split between trains by units of its weight
+    pub fn set_divisible(&mut self, divisible: bool) {
+        let weight = self.size.get(DEFAULT_DIMENSION);
+        let delivered = self.is_delivered();
+        self.divisible = divisible && weight > 0;
+        self.quantity = if self.divisible { weight } else { 1 };
+        self.total = self.quantity;
+        self.delivered = if delivered { self.total } else { 0 };
+    }
+
+    pub fn is_divisible(&self) -> bool {
+        self.divisible
+    }
+
+    pub fn get_quantity(&self) -> u64 {
+        self.quantity
+    }
+
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn get_delivered(&self) -> u64 {
+        self.delivered
     }
 
-    pub fn get_arrived(&self) -> bool {
-        self.arrived
+    /// Function to check whether every unit of the package has reached the destination
+    pub fn is_delivered(&self) -> bool {
+        self.delivered >= self.total
+    }
+
+    /// Function to calculate the size of some units of this package
+    pub fn get_part_size(&self, quantity: u64) -> Dimensions {
+        self.size.scale(quantity, self.quantity)
+    }
+
+    /// Function to get the smallest size a train must have room for to carry this package
+    pub fn get_smallest_part_size(&self) -> Dimensions {
+        match self.divisible {
+            true => self.get_part_size(1),
+            false => self.get_size(),
+        }
+    }
+
+    /// Function to split some units off this package so that they can be loaded on a train
+    /// The rest stays with this package, waiting for another train
+    pub fn split(&mut self, quantity: u64, parent: Arc<Mutex<Package>>) -> Package {
+        let size = self.get_part_size(quantity);
+        self.size.sub(&size);
+        self.quantity -= quantity;
+        self.part += 1;
+        let mut part = self.new_part(format!("{}/{}", self.name, self.part), parent);
+        part.size = size;
+        part.quantity = quantity;
+        part.total = quantity;
+        part
+    }
+
+    /// Function to create an empty part of this package, going the same way
+    pub fn new_part(&self, name: String, parent: Arc<Mutex<Package>>) -> Package {
+        Self {
+            name,
+            size: Dimensions::new(),
+            start: self.start.clone(),
+            end: self.end.clone(),
+            divisible: true,
+            quantity: 0,
+            total: 0,
+            delivered: 0,
+            parent: Some(parent),
+            part: 0,
+            requirement: self.requirement.clone(),
+            itinerary: self.itinerary.clone(),
+            leg: self.leg,
+            release: self.release,
+            deadline: self.deadline,
+            delivered_at: None,
+        }
+    }
+
+    pub fn get_parent(&self) -> Option<Arc<Mutex<Package>>> {
+        self.parent.clone()
+    }
+
+    /// Function to describe the state of the package during the simulation as fields of a snapshot
+    pub fn save_state(&self) -> String {
+        format!(
+            "size={},quantity={},total={},delivered={},part={},leg={},delivered_at={}",
+            self.size,
+            self.quantity,
+            self.total,
+            self.delivered,
+            self.part,
+            self.leg,
+            self.delivered_at.map_or("".to_string(), |x| x.to_string())
+        )
+    }
+
+    /// Function to continue from the state saved by `save_state`
+    /// Returns the reason the state is not valid, naming the field
+    pub fn restore_state(&mut self, options: &HashMap<String, String>) -> Result<(), String> {
+        self.size = parse_field(options, "size")?;
+        self.quantity = parse_field(options, "quantity")?;
+        self.total = parse_field(options, "total")?;
+        self.delivered = parse_field(options, "delivered")?;
+        self.part = parse_field(options, "part")?;
+        self.leg = parse_field(options, "leg")?;
+        self.delivered_at = parse_optional_field(options, "delivered_at")?;
+        Ok(())
     }
 
     /// Function to set the package as delivered
-    pub fn arrive(&mut self) {
-        self.arrived = true;
+    pub fn arrive(&mut self, time: u64) {
+        self.add_delivered(self.quantity, time);
+    }
+
+    /// Function to count units as delivered, also for the package this one was split off from
+    fn add_delivered(&mut self, quantity: u64, time: u64) {
+        self.delivered += quantity;
+        if self.is_delivered() {
+            self.delivered_at = Some(time);
+        }
+        if let Some(parent) = &self.parent {
+            parent.lock().unwrap().add_delivered(quantity, time);
+        }
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<u64>) {
+        self.deadline = deadline;
+    }
+
+    pub fn get_deadline(&self) -> Option<u64> {
+        self.deadline
+    }
+
+    pub fn get_delivered_at(&self) -> Option<u64> {
+        self.delivered_at
+    }
+
+    /// Function to check whether the package was not delivered by its deadline
+    pub fn is_late(&self) -> bool {
+        match (self.deadline, self.delivered_at) {
+            (Some(deadline), Some(time)) => time > deadline,
+            (Some(_), None) => true,
+            _ => false,
+        }
     }
 }
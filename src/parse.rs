@@ -0,0 +1,249 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+/// Function to parse the optional `key=value` fields which follow the mandatory fields of an input line
+/// A field without `=` is a flag and is given an empty value
+pub fn parse_options(fields: &[String]) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    for f in fields {
+        match f.split_once('=') {
+            Some((key, value)) => options.insert(key.trim().to_string(), value.trim().to_string()),
+            None => options.insert(f.trim().to_string(), "".to_string()),
+        };
+    }
+    options
+}
+
+/// Function to parse a list of values separated by `|`, e.g. `hazardous|refrigerated`
+pub fn parse_list(value: Option<&String>) -> HashSet<String> {
+    parse_sequence(value).into_iter().collect()
+}
+
+/// Function to parse a list of pairs of times separated by `|`, e.g. `10:20|40:50`
+pub fn parse_periods(value: Option<&String>) -> Vec<(u64, u64)> {
+    let mut ret = vec![];
+    for p in parse_list(value) {
+        let (from, to) = p.split_once(':').unwrap();
+        ret.push((from.parse().unwrap(), to.parse().unwrap()));
+    }
+    ret.sort();
+    ret
+}
+
+/// Function to parse a list of values separated by `|` keeping their order, e.g. `K1|K2`
+pub fn parse_sequence(value: Option<&String>) -> Vec<String> {
+    match value {
+        Some(v) => v
+            .split('|')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Function to get a field of a snapshot line, e.g. `time=40`
+/// Returns the reason the field is not valid, naming it
+pub fn parse_field<T: FromStr>(options: &HashMap<String, String>, key: &str) -> Result<T, String> {
+    let value = options
+        .get(key)
+        .ok_or_else(|| format!("{} is missing", key))?;
+    value
+        .parse()
+        .map_err(|_| format!("{} is not valid: {}", key, value))
+}
+
+/// Function to get a field of a snapshot line which is empty if it has no value, e.g. `delivered_at=`
+pub fn parse_optional_field<T: FromStr>(
+    options: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<T>, String> {
+    match options.get(key).map(|x| x.as_str()) {
+        Some("") => Ok(None),
+        _ => parse_field(options, key).map(Some),
+    }
+}
+
+/// Function to check a station line before it is parsed, e.g. `B,dwell=2,platforms=1`
+/// Returns the reason the line is not valid, naming the field
+pub fn check_station(line: &str) -> Result<(), String> {
+    let field = split_line(line, 1, "name")?;
+    let options = parse_options(&field[1..]);
+    for key in ["dwell", "handling", "platforms"] {
+        check_number(key, options.get(key))?;
+    }
+    // `refuel` without a time refuels instantly
+    check_number("refuel", options.get("refuel").filter(|x| !x.is_empty()))
+}
+
+/// Function to check an edge line before it is parsed, e.g. `E1,A,B,30,capacity=1`
+/// `is_station` tells whether a station of this name exists
+/// Returns the reason the line is not valid, naming the field
+pub fn check_edge(line: &str, is_station: impl Fn(&str) -> bool) -> Result<(), String> {
+    let field = split_line(line, 4, "name,from,to,journey time")?;
+    check_station_name("from", &field[1], &is_station)?;
+    check_station_name("to", &field[2], &is_station)?;
+    check_number("journey time", Some(&field[3]))?;
+    let options = parse_options(&field[4..]);
+    check_number("capacity", options.get("capacity"))?;
+    for key in ["closed", "journey"] {
+        for p in parse_sequence(options.get(key)) {
+            check_pair(key, &p)?;
+        }
+    }
+    if let Some(delay) = options.get("delay") {
+        check_pair("delay", delay)?;
+    }
+    Ok(())
+}
+
+/// Function to check a train line before it is parsed, e.g. `Q1,6,B,shift=480`
+/// `is_station` tells whether a station of this name exists
+/// Returns the reason the line is not valid, naming the field
+pub fn check_train(line: &str, is_station: impl Fn(&str) -> bool) -> Result<(), String> {
+    let field = split_line(line, 3, "name,capacity,station")?;
+    check_dimensions("capacity", &field[1])?;
+    check_station_name("station", &field[2], &is_station)?;
+    let options = parse_options(&field[3..]);
+    for key in ["fuel", "burn", "burn_load", "speed"] {
+        check_decimal(key, options.get(key))?;
+    }
+    // The travel time is divided by the speed, and no trip adds fuel
+    check_bound("speed", options.get("speed"), false)?;
+    for key in ["fuel", "burn", "burn_load"] {
+        check_bound(key, options.get(key), true)?;
+    }
+    check_number("shift", options.get("shift"))?;
+    if let Some(rest) = options.get("rest") {
+        check_pair("rest", rest)?;
+    }
+    // `home` without a station returns to where the train started
+    match options.get("home").filter(|x| !x.is_empty()) {
+        Some(home) => check_station_name("home", home, &is_station),
+        None => Ok(()),
+    }
+}
+
+/// Function to check a package line before it is parsed, e.g. `K1,5,A,C,deadline=90`
+/// `is_station` tells whether a station of this name exists
+/// Returns the reason the line is not valid, naming the field
+pub fn check_package(line: &str, is_station: impl Fn(&str) -> bool) -> Result<(), String> {
+    let field = split_line(line, 4, "name,weight,from,to")?;
+    check_dimensions("weight", &field[1])?;
+    check_station_name("from", &field[2], &is_station)?;
+    check_station_name("to", &field[3], &is_station)?;
+    let options = parse_options(&field[4..]);
+    for key in ["deadline", "release"] {
+        check_number(key, options.get(key))?;
+    }
+    Ok(())
+}
+
+/// Function to split a line into its fields, checking the mandatory fields are there and it has a name
+fn split_line(line: &str, mandatory: usize, expected: &str) -> Result<Vec<String>, String> {
+    let field = line
+        .split(',')
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>();
+    if field.len() < mandatory {
+        return Err(format!("expected {}", expected));
+    }
+    if field[0].trim().is_empty() {
+        return Err("the name is empty".to_string());
+    }
+    Ok(field)
+}
+
+fn check_station_name(
+    key: &str,
+    station: &str,
+    is_station: &impl Fn(&str) -> bool,
+) -> Result<(), String> {
+    match is_station(station) {
+        true => Ok(()),
+        false => Err(format!("{} is not a known station: {}", key, station)),
+    }
+}
+
+/// Function to check that an optional field is a whole number
+pub fn check_number(key: &str, value: Option<&String>) -> Result<(), String> {
+    match value.map(|v| v.parse::<u64>()) {
+        Some(Err(_)) => Err(format!("{} is not a whole number: {}", key, value.unwrap())),
+        _ => Ok(()),
+    }
+}
+
+/// Function to check that an optional field is a number, e.g. `1.5`
+pub fn check_decimal(key: &str, value: Option<&String>) -> Result<(), String> {
+    match value.map(|v| v.parse::<f64>()) {
+        Some(Err(_)) => Err(format!("{} is not a number: {}", key, value.unwrap())),
+        _ => Ok(()),
+    }
+}
+
+/// Function to check that an optional number is finite and greater than 0, or also 0 if `zero` is allowed
+fn check_bound(key: &str, value: Option<&String>, zero: bool) -> Result<(), String> {
+    match value.and_then(|v| v.parse::<f64>().ok()) {
+        Some(v) if !v.is_finite() || v < 0.0 || (v == 0.0 && !zero) => {
+            let bound = if zero { "at least" } else { "greater than" };
+            Err(format!("{} is not {} 0: {}", key, bound, value.unwrap()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Function to check a pair of whole numbers, e.g. `240:30`
+fn check_pair(key: &str, value: &str) -> Result<(), String> {
+    match value.split_once(':') {
+        Some((a, b)) if a.trim().parse::<u64>().is_ok() && b.trim().parse::<u64>().is_ok() => {
+            Ok(())
+        }
+        _ => Err(format!("{} is not a pair of whole numbers: {}", key, value)),
+    }
+}
+
+/// Function to check a size or capacity, e.g. `5` or `weight:5;volume:2`
+pub fn check_dimensions(key: &str, value: &str) -> Result<(), String> {
+    for d in value.split(';').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        let amount = d.split_once(':').map_or(d, |(_, amount)| amount.trim());
+        if amount.parse::<u64>().is_err() {
+            return Err(format!("{} is not a valid size: {}", key, value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_station(s: &str) -> bool {
+        ["A", "B"].contains(&s)
+    }
+
+    #[test]
+    fn train_with_a_speed_of_0_or_negative_fuel_is_not_valid() {
+        assert_eq!(
+            check_train("Q1,6,A,speed=0", is_station),
+            Err("speed is not greater than 0: 0".to_string())
+        );
+        assert_eq!(
+            check_train("Q1,6,A,speed=inf", is_station),
+            Err("speed is not greater than 0: inf".to_string())
+        );
+        assert_eq!(
+            check_train("Q1,6,A,fuel=-5", is_station),
+            Err("fuel is not at least 0: -5".to_string())
+        );
+        assert_eq!(
+            check_train("Q1,6,A,burn_load=NaN", is_station),
+            Err("burn_load is not at least 0: NaN".to_string())
+        );
+        assert_eq!(
+            check_train("Q1,6,A,speed=0.5,fuel=0,burn=0", is_station),
+            Ok(())
+        );
+    }
+}
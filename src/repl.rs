@@ -0,0 +1,352 @@
+use std::{
+    collections::HashSet,
+    io::{stdout, BufRead, Write},
+    panic::{catch_unwind, set_hook, take_hook, AssertUnwindSafe},
+};
+
+use crate::{
+    network::Network,
+    parse::{check_edge, check_package, check_station, check_train},
+    train::TrainClass,
+};
+
+/// The kinds of lines of the input, in the order they are entered
+const KIND: [&str; 4] = ["station", "edge", "package", "train"];
+
+/// An interactive session to change a scenario and simulate it again
+/// The scenario is kept as the lines of the input, so every change is checked when the network
+/// is built again from them
+pub struct Repl {
+    /// Lines of the stations, edges, packages and trains
+    line: [Vec<String>; 4],
+    /// The network of the last simulation, if the scenario has not changed since
+    network: Option<Network>,
+}
+
+impl Repl {
+    pub fn new(
+        node: Vec<String>,
+        edge: Vec<String>,
+        package: Vec<String>,
+        train: Vec<String>,
+    ) -> Self {
+        Self {
+            line: [node, edge, package, train],
+            network: None,
+        }
+    }
+
+    /// Function to read commands until the input ends or `quit` is entered
+    /// `build` creates the network from the lines of the stations, edges, trains and packages
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        build: impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+    ) {
+        println!("Enter `help` for the list of commands");
+        prompt();
+        for command in input.lines() {
+            let command = command.unwrap();
+            let word = command.split_whitespace().collect::<Vec<&str>>();
+            if word.first() == Some(&"quit") {
+                return;
+            }
+            if !word.is_empty() {
+                if let Err(e) = self.execute(&word, &build) {
+                    println!("Error: {}", e);
+                }
+            }
+            prompt();
+        }
+        println!();
+    }
+
+    /// Function to execute one command, given as its words
+    fn execute(
+        &mut self,
+        word: &[&str],
+        build: &impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+    ) -> Result<(), String> {
+        match word {
+            ["help"] => {
+                println!(
+                    "add <kind> <fields>      e.g. `add edge E1 A B 30`, options as `key=value`"
+                );
+                println!("remove <kind> <name>     e.g. `remove train Q2`");
+                println!("move <kind> <name> <to>  the pick up station of a package or the start of a train");
+                println!("show <kind> <name>       e.g. `show node J`");
+                println!("list                     every line of the scenario");
+                println!("route <from> <to>        the shortest path between two stations");
+                println!("simulate                 simulate the scenario and report the problems");
+                println!("history                  the output of the last simulation");
+                println!("quit");
+                println!("<kind> is station (or node), edge, package or train");
+            }
+            ["add", kind, field @ ..] if !field.is_empty() => {
+                let kind = kind_index(kind)?;
+                if self.find(kind, field[0]).is_some() {
+                    return Err(format!("{} {} already exists", KIND[kind], field[0]));
+                }
+                self.change(build, |line| line[kind].push(field.join(",")))?;
+            }
+            ["remove", kind, name] => {
+                let kind = kind_index(kind)?;
+                let i = self
+                    .find(kind, name)
+                    .ok_or(format!("No {} {}", KIND[kind], name))?;
+                if let Some(user) = self.find_user(name).filter(|_| KIND[kind] == "station") {
+                    return Err(format!("Station {} is used by {}", name, user));
+                }
+                self.change(build, |line| {
+                    line[kind].remove(i);
+                })?;
+            }
+            ["move", kind, name, to] => {
+                let kind = kind_index(kind)?;
+                if !matches!(KIND[kind], "package" | "train") {
+                    return Err(format!("A {} cannot be moved", KIND[kind]));
+                }
+                let i = self
+                    .find(kind, name)
+                    .ok_or(format!("No {} {}", KIND[kind], name))?;
+                self.change(build, |line| {
+                    let mut field = line[kind][i].split(',').collect::<Vec<&str>>();
+                    // The pick up station of a package and the initial station of a train
+                    field[2] = to;
+                    line[kind][i] = field.join(",");
+                })?;
+            }
+            ["show", kind, name] => {
+                let kind = kind_index(kind)?;
+                let i = self
+                    .find(kind, name)
+                    .ok_or(format!("No {} {}", KIND[kind], name))?;
+                println!("{}", self.line[kind][i]);
+                if KIND[kind] == "station" {
+                    let network = try_build(&self.line, build)?;
+                    network.calculate_shortest_distance_between_packages();
+                    println!("{:#?}", network.get_node(name.to_string()).lock().unwrap());
+                }
+            }
+            ["list"] => {
+                for (kind, line) in KIND.iter().zip(self.line.iter()) {
+                    for l in line {
+                        println!("{} {}", kind, l);
+                    }
+                }
+            }
+            ["route", from, to] => {
+                for name in [from, to] {
+                    if self.find(0, name).is_none() {
+                        return Err(format!("No station {}", name));
+                    }
+                }
+                let network = try_build(&self.line, build)?;
+                let (distance, prev) =
+                    network.dijkstra(network.get_node(from.to_string()), &TrainClass::default());
+                if distance[*to] == i64::MAX {
+                    return Err(format!("{} cannot be reached from {}", to, from));
+                }
+                let mut path = vec![to.to_string()];
+                while let Some(Some(p)) = prev.get(path.last().unwrap()) {
+                    path.push(p.clone());
+                }
+                path.reverse();
+                println!("{}, journey time {}", path.join(" -> "), distance[*to]);
+            }
+            ["simulate"] => {
+                let mut network = try_build(&self.line, build)?;
+                for d in network.diagnose() {
+                    println!("Warning: {}", d);
+                }
+                network.calculate_shortest_distance_between_packages();
+                let result = catch(|| network.simulate())
+                    .map_err(|e| format!("The simulation failed: {}", e))?;
+                println!("Makespan: {}", network.get_makespan());
+                if let Err(e) = result {
+                    println!("Simulation stopped: {}", e);
+                }
+                for r in network.result_report() {
+                    println!("{}", r);
+                }
+                for v in network.validate_plan() {
+                    println!("Invalid plan: {}", v);
+                }
+                self.network = Some(network);
+            }
+            ["history"] => match &self.network {
+                Some(network) => network.print_history(),
+                None => return Err("Nothing simulated since the last change".to_string()),
+            },
+            _ => return Err("Unknown command, enter `help` for the list of commands".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Function to find the line of a station, edge, package or train by its name
+    fn find(&self, kind: usize, name: &str) -> Option<usize> {
+        self.line[kind]
+            .iter()
+            .position(|l| l.split(',').next() == Some(name))
+    }
+
+    /// Function to find an edge, package or train which refers to a station
+    fn find_user(&self, station: &str) -> Option<String> {
+        self.line[1..].iter().flatten().find_map(|l| {
+            let mut field = l.split(',');
+            let name = field.next()?;
+            field
+                .any(|f| f == station || f.split('=').nth(1) == Some(station))
+                .then(|| name.to_string())
+        })
+    }
+
+    /// Function to change the lines of the scenario, which is only kept if the network can still be built
+    fn change(
+        &mut self,
+        build: &impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+        f: impl FnOnce(&mut [Vec<String>; 4]),
+    ) -> Result<(), String> {
+        let mut line = self.line.clone();
+        f(&mut line);
+        try_build(&line, build)?;
+        self.line = line;
+        self.network = None;
+        Ok(())
+    }
+}
+
+/// Function to find the kind of line a command refers to
+fn kind_index(kind: &str) -> Result<usize, String> {
+    let kind = if kind == "node" { "station" } else { kind };
+    KIND.iter()
+        .position(|k| *k == kind)
+        .ok_or(format!("Unknown kind {}", kind))
+}
+
+/// Function to build the network from the lines of the scenario
+/// The lines are checked first so that a malformed line or an unknown station is reported by its field
+fn try_build(
+    line: &[Vec<String>; 4],
+    build: &impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+) -> Result<Network, String> {
+    check_lines(line).map_err(|e| format!("The scenario is not valid: {}", e))?;
+    let [node, edge, package, train] = line.clone();
+    catch(|| build(node, edge, train, package))
+        .map_err(|e| format!("The scenario is not valid: {}", e))
+}
+
+/// Function to check every line of the scenario
+/// Returns the first line which is not valid and why
+fn check_lines(line: &[Vec<String>; 4]) -> Result<(), String> {
+    let station = line[0]
+        .iter()
+        .map(|l| l.split(',').next().unwrap_or_default().to_string())
+        .collect::<HashSet<String>>();
+    let is_station = |s: &str| station.contains(s);
+    for (kind, lines) in KIND.iter().zip(line.iter()) {
+        for l in lines {
+            let result = match *kind {
+                "station" => check_station(l),
+                "edge" => check_edge(l, is_station),
+                "package" => check_package(l, is_station),
+                _ => check_train(l, is_station),
+            };
+            result.map_err(|e| format!("{} {}: {}", kind, l, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Function to run a part of the program which panics on a problem it cannot handle, e.g. parsing
+/// or simulating, so the panic is turned into an error instead of ending the session
+fn catch<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    let hook = take_hook();
+    set_hook(Box::new(|_| {}));
+    let ret = catch_unwind(AssertUnwindSafe(f));
+    set_hook(hook);
+    ret.map_err(|e| {
+        e.downcast_ref::<String>()
+            .cloned()
+            .or_else(|| e.downcast_ref::<&str>().map(|x| x.to_string()))
+            .unwrap_or_default()
+    })
+}
+
+fn prompt() {
+    print!("> ");
+    stdout().flush().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+
+    fn repl() -> Repl {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        Repl::new(
+            lines(&["A", "B"]),
+            lines(&["E1,A,B,5"]),
+            lines(&["K1,5,A,B"]),
+            lines(&["Q1,6,A"]),
+        )
+    }
+
+    #[test]
+    fn commands_change_the_scenario() {
+        let mut repl = repl();
+        let input = "add station C\nadd edge E2 B C 5\nmove package K1 C\nremove train Q1\nadd train Q2 6 C\nquit\nadd station D\n";
+        repl.run(input.as_bytes(), create_network);
+        assert_eq!(
+            repl.line,
+            [
+                vec!["A", "B", "C"],
+                vec!["E1,A,B,5", "E2,B,C,5"],
+                vec!["K1,5,C,B"],
+                vec!["Q2,6,C"],
+            ]
+        );
+    }
+
+    #[test]
+    fn change_which_is_not_valid_is_not_kept() {
+        let mut repl = repl();
+        let build = &create_network;
+        assert_eq!(
+            repl.execute(&["add", "edge", "E2", "B", "C", "5"], build),
+            Err(
+                "The scenario is not valid: edge E2,B,C,5: to is not a known station: C"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            repl.execute(&["add", "station", "A"], build),
+            Err("station A already exists".to_string())
+        );
+        assert_eq!(
+            repl.execute(&["remove", "station", "B"], build),
+            Err("Station B is used by E1".to_string())
+        );
+        assert_eq!(
+            repl.execute(&["move", "edge", "E1", "B"], build),
+            Err("A edge cannot be moved".to_string())
+        );
+        assert_eq!(repl.line, self::repl().line);
+    }
+
+    #[test]
+    fn history_is_kept_until_the_scenario_changes() {
+        let mut repl = repl();
+        let build = &create_network;
+        assert!(repl.execute(&["history"], build).is_err());
+        repl.execute(&["simulate"], build).unwrap();
+        assert_eq!(repl.network.as_ref().unwrap().get_makespan(), 5);
+        assert!(repl.execute(&["history"], build).is_ok());
+        repl.execute(&["add", "station", "C"], build).unwrap();
+        assert_eq!(
+            repl.execute(&["history"], build),
+            Err("Nothing simulated since the last change".to_string())
+        );
+    }
+}
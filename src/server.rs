@@ -0,0 +1,335 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    panic::{catch_unwind, AssertUnwindSafe},
+    time::Duration,
+};
+
+use crate::{json::Json, network::Network};
+
+/// Largest body of a request accepted, in bytes
+const MAX_BODY: usize = 1 << 20;
+/// Longest request line or header accepted, in bytes
+const MAX_LINE: u64 = 8 << 10;
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 100;
+/// Time a connection may take to send its request or to receive the response
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Most steps a scenario is simulated for, so that a scenario cannot keep the server busy
+const MAX_STEPS: u64 = 1_000_000;
+
+/// A small HTTP server to submit scenarios and fetch the results of their simulation as JSON
+///
+/// - `POST /scenarios` simulates the scenario in the body and returns its result
+/// - `GET /scenarios` lists the ids of the scenarios simulated so far
+/// - `GET /scenarios/<id>` returns the result of a scenario
+/// - `GET /scenarios/<id>/history` and `GET /scenarios/<id>/kpis` return a part of it
+///
+/// A scenario is an object with the lines of the input, e.g.
+/// `{"stations": ["A", "B"], "edges": ["E1,A,B,30"], "packages": ["K1,5,A,B"], "trains": ["Q1,6,B"]}`
+/// Requests are handled one at a time, so the size of a request, the time to send it and the length of
+/// a simulation are limited
+pub struct Server {
+    listener: TcpListener,
+    /// The result of every scenario simulated so far, the id of a scenario is its index
+    result: Vec<Json>,
+}
+
+/// A request read from a connection
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+/// A response to be written to a connection
+struct Response {
+    status: u16,
+    body: Json,
+}
+
+impl Response {
+    fn new(status: u16, body: Json) -> Self {
+        Self { status, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::new(status, Json::object(vec![("error", message.into())]))
+    }
+}
+
+impl Server {
+    /// Function to listen on an address, e.g. `127.0.0.1:8080`
+    pub fn new(address: &str) -> Self {
+        Self {
+            listener: TcpListener::bind(address).unwrap(),
+            result: vec![],
+        }
+    }
+
+    pub fn get_address(&self) -> String {
+        self.listener.local_addr().unwrap().to_string()
+    }
+
+    /// Function to handle requests until the process is stopped
+    /// `build` creates the network from the lines of the stations, edges, trains and packages
+    pub fn run(
+        &mut self,
+        build: impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+    ) {
+        for stream in self.listener.try_clone().unwrap().incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            if stream.set_read_timeout(Some(TIMEOUT)).is_err()
+                || stream.set_write_timeout(Some(TIMEOUT)).is_err()
+            {
+                continue;
+            }
+            let response = match read_request(&mut stream) {
+                Ok(request) => self.handle(request, &build),
+                Err(e) => Response::error(400, &e),
+            };
+            // The client may have gone away, which only concerns that client
+            let _ = write_response(&mut stream, response);
+        }
+    }
+
+    /// Function to route a request to the matching endpoint
+    fn handle(
+        &mut self,
+        request: Request,
+        build: &impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+    ) -> Response {
+        let path = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .collect::<Vec<&str>>();
+        if path[0] != "scenarios" {
+            return Response::error(404, "Not found");
+        }
+        match (request.method.as_str(), path.len()) {
+            ("POST", 1) => self.post_scenario(&request.body, build),
+            ("GET", 1) => Response::new(
+                200,
+                Json::object(vec![(
+                    "scenarios",
+                    (0..self.result.len() as u64).collect::<Vec<u64>>().into(),
+                )]),
+            ),
+            ("GET", 2 | 3) => {
+                let result = match path[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| self.result.get(i))
+                {
+                    Some(r) => r,
+                    None => return Response::error(404, "Scenario not found"),
+                };
+                match path.get(2).copied() {
+                    None => Response::new(200, result.clone()),
+                    Some(part @ ("history" | "kpis")) => {
+                        Response::new(200, result.get(part).unwrap().clone())
+                    }
+                    Some(_) => Response::error(404, "Not found"),
+                }
+            }
+            (_, 1..=3) => Response::error(405, "Method not allowed"),
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    /// Function to simulate the scenario in the body of a request and keep its result
+    fn post_scenario(
+        &mut self,
+        body: &str,
+        build: &impl Fn(Vec<String>, Vec<String>, Vec<String>, Vec<String>) -> Network,
+    ) -> Response {
+        let scenario = match Json::parse(body) {
+            Ok(s) => s,
+            Err(e) => return Response::error(400, &e),
+        };
+        let mut lines = vec![];
+        for key in ["stations", "edges", "trains", "packages"] {
+            let list = scenario.get(key).and_then(|l| l.as_array()).map(|l| {
+                l.iter()
+                    .map(|x| x.as_str().map(|x| x.to_string()))
+                    .collect()
+            });
+            match list {
+                Some(Some(l)) => lines.push(l),
+                _ => return Response::error(400, &format!("{} must be a list of strings", key)),
+            }
+        }
+        let package = lines.pop().unwrap();
+        let train = lines.pop().unwrap();
+        let edge = lines.pop().unwrap();
+        let node = lines.pop().unwrap();
+        // The input is parsed the same way as on the terminal, which panics on a malformed line
+        let network = catch_unwind(AssertUnwindSafe(|| {
+            let mut network = build(node, edge, train, package);
+            let warnings = network.diagnose();
+            network.calculate_shortest_distance_between_packages();
+            let result = network.simulate_steps(MAX_STEPS);
+            (network, warnings, result)
+        }));
+        let (network, warnings, result) = match network {
+            Ok(n) => n,
+            Err(_) => return Response::error(400, "Invalid scenario"),
+        };
+        let id = self.result.len() as u64;
+        let error: Json = result.err().into();
+        let warnings: Json = warnings.into();
+        let mut ret = network.to_json();
+        ret.set("id", id.into());
+        ret.set("warnings", warnings.clone());
+        ret.set("error", error.clone());
+        self.result.push(ret);
+        Response::new(
+            201,
+            Json::object(vec![
+                ("id", id.into()),
+                ("warnings", warnings),
+                ("error", error),
+            ]),
+        )
+    }
+}
+
+/// Function to read the request line, the headers and the body of a request
+fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut request_line = line.split_whitespace();
+    let method = request_line.next().ok_or("Empty request")?.to_string();
+    let path = request_line.next().ok_or("Missing path")?.to_string();
+    let mut length = 0;
+    for i in 0.. {
+        if i == MAX_HEADERS {
+            return Err("Too many headers".to_string());
+        }
+        line.clear();
+        read_line(&mut reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = header.split_once(':') {
+            if key.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| "Invalid Content-Length")?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(format!("Body is larger than {} bytes", MAX_BODY));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok(Request {
+        method,
+        path,
+        body: String::from_utf8(body).map_err(|_| "Body is not UTF-8")?,
+    })
+}
+
+/// Function to read a line of the request line or the headers, which must end within `MAX_LINE` bytes
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<(), String> {
+    reader
+        .take(MAX_LINE)
+        .read_line(line)
+        .map_err(|e| e.to_string())?;
+    if !line.ends_with('\n') && line.len() as u64 == MAX_LINE {
+        return Err("Line is too long".to_string());
+    }
+    Ok(())
+}
+
+/// Function to write a response with a JSON body and close the connection
+fn write_response(stream: &mut TcpStream, response: Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+    use std::thread;
+
+    /// Function to start a server on a free port, returning its address
+    fn start() -> String {
+        let mut server = Server::new("127.0.0.1:0");
+        let address = server.get_address();
+        thread::spawn(move || server.run(create_network));
+        address
+    }
+
+    fn send(address: &str, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn post(address: &str, body: &str) -> String {
+        send(
+            address,
+            &format!(
+                "POST /scenarios HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        )
+    }
+
+    #[test]
+    fn scenario_is_simulated_and_its_result_fetched() {
+        let address = start();
+        let response = post(
+            &address,
+            r#"{"stations": ["A", "B"], "edges": ["E1,A,B,30"], "packages": ["K1,5,A,B"], "trains": ["Q1,6,A"]}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 201 Created"));
+        assert!(response.ends_with(r#"{"id":0,"warnings":[],"error":null}"#));
+        let response = send(&address, "GET /scenarios/0/history HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with(
+            r#"[{"w":0,"t":"Q1","n1":"A","p1":["K1"],"n2":"B","p2":["K1"],"arrival":30}]"#
+        ));
+        let response = send(&address, "GET /scenarios/1 HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn request_which_is_too_large_is_rejected() {
+        let address = start();
+        let response = send(
+            &address,
+            "POST /scenarios HTTP/1.1\r\nContent-Length: 1000000000000\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.ends_with(r#"{"error":"Body is larger than 1048576 bytes"}"#));
+        let response = post(&address, &("[".repeat(1000) + &"]".repeat(1000)));
+        assert!(response.ends_with(r#"{"error":"Invalid JSON at 64: nested too deeply"}"#));
+    }
+}
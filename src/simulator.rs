@@ -0,0 +1,258 @@
+use crate::{dimension::Dimensions, network::Network, train::Location};
+
+/// Runs the simulation of a network step by step so that it can be paused and inspected
+/// between the steps
+/// The shortest paths of the network have to be calculated before the first step
+pub struct Simulator {
+    network: Network,
+    /// The error which stopped the simulation, if any
+    error: Option<String>,
+}
+
+/// A read-only view of a train between two steps of the simulation
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainState {
+    pub name: String,
+    /// Name of the node or edge the train is on
+    pub location: String,
+    /// Whether the train is on an edge rather than at a node
+    pub on_edge: bool,
+    /// Time the train has travelled on its current edge
+    pub time_on_edge: u64,
+    /// Time the trip on its current edge takes
+    pub trip_time: u64,
+    pub load: Dimensions,
+    pub capacity: Dimensions,
+    /// Names of the packages on the train in the order they were loaded
+    pub package: Vec<String>,
+    /// Nodes the train will pass on the way to its target, ending with the target
+    pub route: Vec<String>,
+    /// Whether the train has ended its trip
+    pub end_trip: bool,
+}
+
+impl Simulator {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            error: None,
+        }
+    }
+
+    /// Function to simulate 1 unit of time
+    /// Returns whether there is anything left to simulate, or the error which stopped the simulation
+    pub fn step(&mut self) -> Result<bool, String> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        match self.network.step() {
+            Ok(running) => Ok(running),
+            Err(e) => {
+                self.error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// Function to simulate until the clock reaches this time or there is nothing left to simulate
+    pub fn run_until(&mut self, time: u64) -> Result<bool, String> {
+        while self.network.get_time() < time {
+            if !self.step()? {
+                return Ok(false);
+            }
+        }
+        Ok(!self.network.is_finished())
+    }
+
+    /// Function to simulate until a train arrives at or leaves a node, or loads or unloads a package
+    pub fn run_until_event(&mut self) -> Result<bool, String> {
+        let before = self.event_key();
+        while self.step()? {
+            if self.event_key() != before {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Function to simulate until there is nothing left to simulate
+    pub fn run(&mut self) -> Result<(), String> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    pub fn get_time(&self) -> u64 {
+        self.network.get_time()
+    }
+
+    pub fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn get_network_mut(&mut self) -> &mut Network {
+        &mut self.network
+    }
+
+    /// Function to get the state of every train, ordered by name
+    pub fn get_train_state(&self) -> Vec<TrainState> {
+        let mut ret = vec![];
+        for t in self.network.sorted_train() {
+            let t = t.lock().unwrap();
+            let (location, on_edge) = match t.get_location() {
+                Location::Node(n) => (n.lock().unwrap().get_name(), false),
+                Location::Edge(e) => (e.lock().unwrap().get_name(), true),
+            };
+            ret.push(TrainState {
+                name: t.get_name(),
+                location,
+                on_edge,
+                time_on_edge: if on_edge { t.get_time_on_edge() } else { 0 },
+                trip_time: t.get_trip_time(),
+                load: t.get_load(),
+                capacity: t.get_capacity(),
+                package: t.get_package_names(),
+                route: t.get_route_path(),
+                end_trip: t.is_not_end(),
+            });
+        }
+        ret
+    }
+
+    /// Function to get the part of the state of the trains which changes on an event
+    fn event_key(&self) -> Vec<(String, Vec<String>)> {
+        self.get_train_state()
+            .into_iter()
+            .map(|t| (t.location, t.package))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_network;
+
+    /// Function to build the simulator of a network from the lines of the input
+    fn simulator(node: &[&str], edge: &[&str], package: &[&str], train: &[&str]) -> Simulator {
+        let lines = |x: &[&str]| x.iter().map(|x| x.to_string()).collect();
+        let network = create_network(lines(node), lines(edge), lines(train), lines(package));
+        network.calculate_shortest_distance_between_packages();
+        Simulator::new(network)
+    }
+
+    /// A divisible package which does not fit on any train as a whole
+    fn split() -> Simulator {
+        simulator(
+            &["A", "B", "C"],
+            &["E1,A,B,30", "E2,B,C,10"],
+            &["K1,weight:10;volume:5,A,C,divisible"],
+            &["Q1,6,B", "Q2,weight:20;volume:1,B"],
+        )
+    }
+
+    fn history(simulator: &Simulator) -> Vec<String> {
+        simulator
+            .get_network()
+            .get_history()
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    fn train(simulator: &Simulator, name: &str) -> TrainState {
+        simulator
+            .get_train_state()
+            .into_iter()
+            .find(|t| t.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn simulation_pauses_at_a_time_and_resumes() {
+        // A station with 1 platform which Q1 takes for 12 while Q2 arrives at 10
+        let mut simulator = simulator(
+            &["A", "B,platforms=1,dwell=12", "C"],
+            &["E1,A,B,10", "E2,B,C,5"],
+            &["K1,5,A,C", "K2,5,B,C"],
+            &["Q1,6,B", "Q2,6,A"],
+        );
+        assert_eq!(simulator.run_until(11), Ok(true));
+        assert_eq!(simulator.get_time(), 11);
+        // Q2 has reached the end of the edge but B is taken by Q1
+        let q2 = train(&simulator, "Q2");
+        assert!(q2.on_edge);
+        assert_eq!(q2.time_on_edge, q2.trip_time);
+        assert_eq!(q2.package, vec!["K1"]);
+        assert_eq!(q2.route, vec!["B", "C"]);
+        assert_eq!(train(&simulator, "Q1").location, "B");
+        simulator.run().unwrap();
+        assert_eq!(simulator.get_network().get_makespan(), 17);
+        assert_eq!(simulator.run_until(100), Ok(false));
+    }
+
+    #[test]
+    fn simulation_runs_until_the_next_event() {
+        // A single track line which Q2 has to wait for until Q1 has left it
+        let mut simulator = simulator(
+            &["A", "B", "C"],
+            &["E1,A,B,10,capacity=1", "E2,B,C,5"],
+            &["K1,5,A,C", "K2,5,B,A"],
+            &["Q1,6,A", "Q2,6,B"],
+        );
+        assert_eq!(simulator.run_until_event(), Ok(true));
+        // Q1 has taken the line, Q2 has loaded K2 but cannot leave
+        assert!(train(&simulator, "Q1").on_edge);
+        let q2 = train(&simulator, "Q2");
+        assert_eq!((q2.location.as_str(), q2.on_edge), ("B", false));
+        assert_eq!(q2.package, vec!["K2"]);
+        simulator.run().unwrap();
+        assert!(train(&simulator, "Q2").end_trip);
+    }
+
+    #[test]
+    fn stopped_simulation_keeps_its_error() {
+        let mut simulator = simulator(&["A", "B"], &[], &["K1,5,A,B"], &["Q1,6,A"]);
+        let error = "Some packages are not reachable".to_string();
+        assert_eq!(simulator.run(), Err(error.clone()));
+        assert_eq!(simulator.step(), Err(error));
+    }
+
+    #[test]
+    fn snapshot_restores_the_same_simulation() {
+        let mut original = split();
+        original.run_until(45).unwrap();
+        let state = original.get_network().save_state();
+        let mut restored = split();
+        restored.get_network_mut().restore_state(&state).unwrap();
+        assert_eq!(restored.get_time(), original.get_time());
+        assert_eq!(restored.get_train_state(), original.get_train_state());
+        original.run().unwrap();
+        restored.run().unwrap();
+        assert_eq!(history(&restored), history(&original));
+        assert_eq!(
+            restored.get_network().get_makespan(),
+            original.get_network().get_makespan()
+        );
+    }
+
+    #[test]
+    fn snapshot_with_a_line_cut_off_is_not_restored() {
+        let mut original = split();
+        original.run_until(20).unwrap();
+        let mut state = original.get_network().save_state();
+        let last = state.pop().unwrap();
+        let cut = last[..last.find("rest=").unwrap()].to_string();
+        state.push(cut.clone());
+        let mut restored = split();
+        assert_eq!(
+            restored.get_network_mut().restore_state(&state),
+            Err(format!("Snapshot line {}: rest is missing", cut))
+        );
+        assert_eq!(
+            restored
+                .get_network_mut()
+                .restore_state(&["train,Q9,location=A".to_string()]),
+            Err("Snapshot line train,Q9,location=A: Q9 is not a known train".to_string())
+        );
+    }
+}
@@ -1,31 +1,89 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display},
     sync::{Arc, Mutex},
 };
 
-use crate::{edge::Edge, node::Node, package::Package};
+use crate::{
+    dimension::{Dimensions, DEFAULT_DIMENSION},
+    edge::Edge,
+    json::Json,
+    node::{Node, ShortestPathToCritical},
+    package::Package,
+    parse::{parse_field, parse_sequence},
+};
 
 /// A train structure
 #[derive(Clone)]
 pub struct Train {
     name: String,
-    /// Maximum load of the train
-    capacity: u64,
+    /// Maximum load of the train on every dimension it is limited by
+    capacity: Dimensions,
     /// The current load of the train
-    load: u64,
+    load: Dimensions,
     /// Packages carried by the train
     package: HashMap<String, Arc<Mutex<Package>>>,
+    /// Names of the packages carried by the train in the order they were loaded
+    stack: Vec<String>,
+    /// Whether only the package loaded last can be unloaded
+    lifo: bool,
+    /// Whether the train takes new work, a train which is not available only delivers what it carries
+    available: bool,
     /// Current location of the train
     location: Location,
     /// A dictionary to navigate the train to next critical node
     route: HashMap<String, Option<String>>,
+    /// The critical node the train is heading to
+    target: Option<Arc<Mutex<Node>>>,
     /// Time travelled on an edge
     time: u64,
+    /// Random delay of the current trip on an edge on top of the travel time
+    trip_delay: u64,
+    /// Time left before the train finishes handling packages at the current node
+    dwell: u64,
+    /// Whether the packages at the current node are handled and the train is ready to leave
+    departing: bool,
+    /// The trains holding the platform or line the train waited for in the last call of `deliver`,
+    /// none if it did not wait
+    blocked: Option<Vec<String>>,
+    /// Whether the train handed over a package at a hub in the last call of `deliver`
+    handed_over: bool,
     /// The travel history of the train
     history: Vec<History>,
     /// Whether the train has no package to deliver
     end_trip: bool,
+    /// Whether the train chooses its target again at the next node it reaches, even if it is not a critical node
+    replan: bool,
+    /// The kind of rolling stock, matched against the requirement of a package
+    tags: HashSet<String>,
+    /// The class and speed of the train which decide the edges it can use and how fast
+    class: TrainClass,
+    /// The depot the train has to return to after its last drop off, if required
+    home: Option<Arc<Mutex<Node>>>,
+    /// Maximum time on duty of the crew including its rests, unlimited if not set
+    shift: Option<u64>,
+    /// Working time after which the crew must rest and the length of the rest
+    rest_rule: Option<(u64, u64)>,
+    /// Total time on duty so far, including the rests
+    duty: u64,
+    /// Working time since the last rest
+    since_rest: u64,
+    /// Time left of the current rest at a station
+    rest: u64,
+    /// Whether the train skipped some work because it could not be finished within the shift
+    shift_limited: bool,
+    /// Descriptions of the shift, rest and fuel rules the train had to break
+    violation: Vec<String>,
+    /// Fuel or battery budget of the train when full, unlimited if not set
+    fuel_capacity: Option<f64>,
+    /// Fuel left
+    fuel: f64,
+    /// Fuel used for every unit of travel time
+    burn: f64,
+    /// Additional fuel used for every unit of travel time and every unit of weight loaded
+    burn_load: f64,
+    /// Time lost to disruptions, waiting for closed lines and travelling longer after a reroute
+    delay: i64,
 }
 
 impl Debug for Train {
@@ -33,14 +91,52 @@ impl Debug for Train {
         f.debug_struct(&self.name)
             .field("Capacity", &self.capacity)
             .field("Load", &self.load)
-            .field("Package", &self.package.keys())
+            .field("Tags", &self.tags)
+            .field("Class", &self.class)
+            .field(
+                "Home",
+                &self.home.as_ref().map(|h| h.lock().unwrap().get_name()),
+            )
+            .field("Package", &self.stack)
+            .field("LIFO", &self.lifo)
             .field("Location", &self.location)
             .field("Time travelled", &self.time)
+            .field("Dwell", &self.dwell)
+            .field("Duty", &self.duty)
+            .field("Rest", &self.rest)
+            .field("Fuel", &self.fuel)
+            .field("Delay", &self.delay)
             .field("End", &self.end_trip)
             .finish()
     }
 }
 
+/// The class of a train decides which edges it may use and its shortest paths
+/// Trains of the same class and speed share the same shortest path tables
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainClass {
+    /// Name of the class, edges can ban a class by this name
+    pub name: String,
+    /// Speed multiplier, a train of speed 2 needs half the journey time of an edge
+    pub speed: f64,
+}
+
+impl Default for TrainClass {
+    fn default() -> Self {
+        Self {
+            name: "".to_string(),
+            speed: 1.0,
+        }
+    }
+}
+
+impl TrainClass {
+    /// Function to get the key of the shortest path tables of this class
+    pub fn key(&self) -> String {
+        format!("{}@{}", self.name, self.speed)
+    }
+}
+
 /// Location enum as a train can either be on an edge or a node
 #[derive(Clone)]
 pub enum Location {
@@ -58,17 +154,42 @@ impl Debug for Location {
 }
 
 impl Train {
-    pub fn new(name: String, capacity: u64, location: Arc<Mutex<Node>>) -> Self {
+    pub fn new(name: String, capacity: Dimensions, location: Arc<Mutex<Node>>) -> Self {
         Self {
             name,
             capacity,
-            load: 0,
+            load: Dimensions::new(),
             package: HashMap::new(),
+            stack: vec![],
+            lifo: false,
+            available: true,
             location: Location::Node(location),
             route: HashMap::new(),
+            target: None,
             time: 0,
+            trip_delay: 0,
+            dwell: 0,
+            departing: false,
+            blocked: None,
+            handed_over: false,
             history: vec![],
             end_trip: false,
+            replan: false,
+            tags: HashSet::new(),
+            class: TrainClass::default(),
+            home: None,
+            shift: None,
+            rest_rule: None,
+            duty: 0,
+            since_rest: 0,
+            rest: 0,
+            shift_limited: false,
+            violation: vec![],
+            fuel_capacity: None,
+            fuel: 0.0,
+            burn: 1.0,
+            burn_load: 0.0,
+            delay: 0,
         }
     }
 
@@ -76,58 +197,444 @@ impl Train {
         self.name.clone()
     }
 
-    pub fn get_capacity(&self) -> u64 {
-        self.capacity
+    pub fn get_capacity(&self) -> Dimensions {
+        self.capacity.clone()
+    }
+
+    pub fn get_load(&self) -> Dimensions {
+        self.load.clone()
+    }
+
+    pub fn get_tags(&self) -> HashSet<String> {
+        self.tags.clone()
+    }
+
+    pub fn set_tags(&mut self, tags: HashSet<String>) {
+        self.tags = tags;
+    }
+
+    pub fn set_lifo(&mut self, lifo: bool) {
+        self.lifo = lifo;
+    }
+
+    pub fn is_lifo(&self) -> bool {
+        self.lifo
+    }
+
+    /// Function to let the train take new work or not, e.g. when it breaks down or is repaired
+    /// A train which becomes available looks for work again
+    pub fn set_available(&mut self, available: bool) {
+        self.available = available;
+        if available {
+            self.wake_up();
+        }
+    }
+
+    /// Function to get the packages which could be unloaded next
+    /// With LIFO loading only the package loaded last can be unloaded
+    fn get_unloadable_package(&self) -> Vec<Arc<Mutex<Package>>> {
+        let name: Vec<String> = match self.lifo {
+            true => self.stack.last().into_iter().cloned().collect(),
+            false => self.stack.clone(),
+        };
+        name.iter().map(|n| self.package[n].clone()).collect()
+    }
+
+    pub fn get_class(&self) -> TrainClass {
+        self.class.clone()
+    }
+
+    pub fn set_class(&mut self, class: TrainClass) {
+        self.class = class;
+    }
+
+    pub fn get_home(&self) -> Option<Arc<Mutex<Node>>> {
+        self.home.clone()
+    }
+
+    pub fn set_home(&mut self, home: Option<Arc<Mutex<Node>>>) {
+        self.home = home;
+    }
+
+    pub fn set_shift(&mut self, shift: Option<u64>, rest_rule: Option<(u64, u64)>) {
+        self.shift = shift;
+        self.rest_rule = rest_rule;
+    }
+
+    /// Function to set the fuel model of the train, which starts with a full tank
+    pub fn set_fuel(&mut self, fuel_capacity: Option<f64>, burn: f64, burn_load: f64) {
+        self.fuel_capacity = fuel_capacity;
+        self.fuel = fuel_capacity.unwrap_or(0.0);
+        self.burn = burn;
+        self.burn_load = burn_load;
+    }
+
+    pub fn get_fuel(&self) -> f64 {
+        self.fuel
+    }
+
+    /// Function to estimate the fuel needed to travel for this time with the current load
+    fn fuel_needed(&self, travel_time: i64) -> f64 {
+        let load = self.load.get(DEFAULT_DIMENSION) as f64;
+        travel_time as f64 * (self.burn + self.burn_load * load)
+    }
+
+    /// Function to use the fuel of 1 unit of travel time
+    fn burn_fuel(&mut self, edge: &Arc<Mutex<Edge>>, time: u64) {
+        if self.fuel_capacity.is_none() {
+            return;
+        }
+        let was_empty = self.fuel < 0.0;
+        self.fuel -= self.fuel_needed(1);
+        if self.fuel < 0.0 && !was_empty {
+            self.violation.push(format!(
+                "ran out of fuel on edge {} at time {}",
+                edge.lock().unwrap().get_name(),
+                time
+            ));
+        }
+    }
+
+    /// Function to find the travel time from a critical node to the nearest refuelling station
+    /// Returns `None` if no refuelling station can be reached
+    fn distance_to_refuel(&self, from: &Arc<Mutex<Node>>) -> Option<i64> {
+        if from.lock().unwrap().get_refuel_time().is_some() {
+            return Some(0);
+        }
+        let paths = from.lock().unwrap().get_shortest_path(&self.class);
+        paths
+            .into_iter()
+            .filter(|x| x.distance_to_destination != i64::MAX)
+            .find(|x| x.destination.lock().unwrap().get_refuel_time().is_some())
+            .map(|x| x.distance_to_destination)
+    }
+
+    /// Function to check whether the train has the fuel to reach a critical node
+    /// and then a refuelling station from there
+    /// If no refuelling station can be reached the fuel left is all the train has, so reaching the node is enough
+    fn has_fuel_for(&self, c: &ShortestPathToCritical) -> bool {
+        if self.fuel_capacity.is_none() {
+            return true;
+        }
+        let to_refuel = self.distance_to_refuel(&c.destination).unwrap_or(0);
+        self.fuel_needed(c.distance_to_destination + to_refuel) <= self.fuel
+    }
+
+    /// Function to check whether the train has the fuel to take a package from a node to its next stop,
+    /// and then to a refuelling station, with the package loaded on top of the current load
+    /// `fuel` is the fuel the train has when it leaves the node
+    fn has_fuel_to_deliver(
+        &self,
+        from: &Arc<Mutex<Node>>,
+        package: &Package,
+        size: &Dimensions,
+        fuel: f64,
+    ) -> bool {
+        if self.fuel_capacity.is_none() {
+            return true;
+        }
+        let stop = package.get_next_stop();
+        let stop_name = stop.lock().unwrap().get_name();
+        let distance = match self.distance_between(from, &stop_name) {
+            Some(d) => d,
+            None => return true,
+        };
+        let to_refuel = self.distance_to_refuel(&stop).unwrap_or(0);
+        let mut load = self.load.clone();
+        load.add(size);
+        let burn = self.burn + self.burn_load * load.get(DEFAULT_DIMENSION) as f64;
+        (distance + to_refuel) as f64 * burn <= fuel
+    }
+
+    /// Function to get the fuel the train has when it leaves a node it reaches after this travel time,
+    /// the tank is filled up at a refuelling station
+    fn fuel_at(&self, node: &Arc<Mutex<Node>>, travel_time: i64) -> f64 {
+        match (self.fuel_capacity, node.lock().unwrap().get_refuel_time()) {
+            (Some(fuel_capacity), Some(_)) => fuel_capacity,
+            _ => self.fuel - self.fuel_needed(travel_time),
+        }
+    }
+
+    /// Function to check whether the train could do the work at a critical node after filling up at a refuelling station
+    /// `package` is the package to pick up there, if the work is not only to reach the node
+    fn can_work_after_refuel(
+        &self,
+        station: &Arc<Mutex<Node>>,
+        node: &Arc<Mutex<Node>>,
+        package: &Option<Arc<Mutex<Package>>>,
+    ) -> bool {
+        let fuel_capacity = match self.fuel_capacity {
+            Some(f) => f,
+            None => return true,
+        };
+        let node_name = node.lock().unwrap().get_name();
+        let fuel = match self.distance_between(station, &node_name) {
+            Some(_) if node.lock().unwrap().get_refuel_time().is_some() => fuel_capacity,
+            Some(d) => fuel_capacity - self.fuel_needed(d),
+            None => return false,
+        };
+        match package {
+            Some(p) => {
+                let p = p.lock().unwrap();
+                let size = p.get_part_size(self.room_for_units(&p));
+                self.has_fuel_to_deliver(node, &p, &size, fuel)
+            }
+            None => self.fuel_needed(self.distance_to_refuel(node).unwrap_or(0)) <= fuel,
+        }
+    }
+
+    pub fn get_duty(&self) -> u64 {
+        self.duty
+    }
+
+    pub fn is_shift_limited(&self) -> bool {
+        self.shift_limited
+    }
+
+    pub fn get_violation(&self) -> Vec<String> {
+        self.violation.clone()
+    }
+
+    /// Function to check whether the trip to a critical node and the handling of the given number of packages there,
+    /// and the way back to the depot if required, can be finished within the shift
+    /// The rests the crew has to take on the way count toward the shift
+    fn fits_in_shift(
+        &self,
+        from: &Arc<Mutex<Node>>,
+        c: &ShortestPathToCritical,
+        handled: usize,
+    ) -> bool {
+        let shift = match self.shift {
+            Some(s) => s,
+            None => return true,
+        };
+        let mut since_rest = self.since_rest;
+        let mut required = match self.time_on_duty(from, c, handled, &mut since_rest) {
+            Some(t) => t,
+            None => return false,
+        };
+        if let Some(home) = &self.home {
+            let home_name = home.lock().unwrap().get_name();
+            if c.destination.lock().unwrap().get_name() != home_name {
+                let back = c
+                    .destination
+                    .lock()
+                    .unwrap()
+                    .get_shortest_path(&self.class)
+                    .into_iter()
+                    .find(|x| x.destination.lock().unwrap().get_name() == home_name);
+                match back.and_then(|b| self.time_on_duty(&c.destination, &b, 0, &mut since_rest)) {
+                    Some(t) => required = required.saturating_add(t),
+                    None => return false,
+                }
+            }
+        }
+        self.duty.saturating_add(required) <= shift
+    }
+
+    /// Function to find the time on duty needed to travel to a critical node and handle the given number of packages there,
+    /// including the rests on the way
+    /// `since_rest` is the working time since the last rest when the trip starts and is updated to when it ends
+    /// Returns `None` if the train cannot get there
+    fn time_on_duty(
+        &self,
+        from: &Arc<Mutex<Node>>,
+        c: &ShortestPathToCritical,
+        handled: usize,
+        since_rest: &mut u64,
+    ) -> Option<u64> {
+        if c.distance_to_destination < 0 || c.distance_to_destination == i64::MAX {
+            return None;
+        }
+        let mut required = c.distance_to_destination as u64;
+        if let Some((after, duration)) = self.rest_rule {
+            let mut node = from.clone();
+            let mut name = node.lock().unwrap().get_name();
+            // The path cannot be longer than the number of nodes it knows
+            for _ in 0..c.path.len() {
+                let next = match c.path.get(&name) {
+                    Some(Some(next)) => next.clone(),
+                    _ => break,
+                };
+                let edge = node.lock().unwrap().get_edge(next);
+                let journey_time = edge.lock().unwrap().get_travel_time(&self.class);
+                if *since_rest > 0 && *since_rest + journey_time > after {
+                    required += duration;
+                    *since_rest = 0;
+                }
+                *since_rest += journey_time;
+                node = edge.lock().unwrap().get_end_node();
+                name = node.lock().unwrap().get_name();
+            }
+        }
+        let service_time = c.destination.lock().unwrap().get_service_time(handled);
+        *since_rest += service_time;
+        Some(required + service_time)
+    }
+
+    /// Function to check whether the crew can take a package from this node to its next stop,
+    /// and back to the depot if required, within the shift
+    fn can_deliver_within_shift(&self, from: &Arc<Mutex<Node>>, package: &Package) -> bool {
+        let stop_name = package.get_next_stop().lock().unwrap().get_name();
+        let paths = from.lock().unwrap().get_shortest_path(&self.class);
+        paths
+            .iter()
+            .filter(|c| c.destination.lock().unwrap().get_name() == stop_name)
+            .all(|c| self.fits_in_shift(from, c, 1))
+    }
+
+    /// Function to find the shortest travel time of this train between two critical nodes
+    /// Returns `None` if the train cannot reach the destination
+    fn distance_between(&self, from: &Arc<Mutex<Node>>, to: &str) -> Option<i64> {
+        from.lock().unwrap().distance_to(&self.class, to)
+    }
+
+    /// Function to check whether the train can take a package from this node to its next stop
+    fn can_deliver_from(&self, from: &Arc<Mutex<Node>>, package: &Package) -> bool {
+        let stop_name = package.get_next_stop().lock().unwrap().get_name();
+        self.can_carry(package) && self.distance_between(from, &stop_name).is_some()
+    }
+
+    /// Function to check whether a package of this size fits on top of the current load
+    pub fn has_room_for(&self, size: &Dimensions) -> bool {
+        self.load.fits(size, &self.capacity)
+    }
+
+    /// Function to check whether the train is allowed to and has room to load a package, or a part of it
+    pub fn can_carry(&self, package: &Package) -> bool {
+        package.is_compatible(&self.tags) && self.room_for_units(package) > 0
+    }
+
+    /// Function to find how many units of a package fit on top of the current load
+    /// A package which is not divisible either fits as a whole or not at all
+    fn room_for_units(&self, package: &Package) -> u64 {
+        if self.has_room_for(&package.get_size()) {
+            return package.get_quantity();
+        }
+        if !package.is_divisible() {
+            return 0;
+        }
+        // A part grows with its units, so search for the largest part which fits
+        let (mut low, mut high) = (0, package.get_quantity() - 1);
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.has_room_for(&package.get_part_size(mid)) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
     }
 
     pub fn get_location(&self) -> &Location {
         &self.location
     }
 
+    /// Function to get the name of the node the train is at, if it is not on an edge
+    pub fn get_node_name(&self) -> Option<String> {
+        match &self.location {
+            Location::Node(n) => Some(n.lock().unwrap().get_name()),
+            Location::Edge(_) => None,
+        }
+    }
+
     /// Function to get a new target for the train if it has already reached a critical node
     pub fn find_new_target(&mut self) {
         self.route = HashMap::new();
+        self.target = None;
         match &self.location {
-            Location::Edge(_) => return,
+            Location::Edge(_) => {}
             Location::Node(n) => {
                 // Loop for all critical node, this vector is sorted
-                let crit = n.lock().unwrap().get_shortest_path();
+                let crit = n.lock().unwrap().get_shortest_path(&self.class);
+                // The work skipped for the lack of fuel, with the package to pick up if any
+                let mut low_fuel = vec![];
+                // A package left here for the lack of fuel can be picked up after refuelling
+                let packages = n.lock().unwrap().get_package();
+                for package in packages {
+                    let p = package.lock().unwrap();
+                    if self.available && !p.is_delivered() && self.can_deliver_from(n, &p) {
+                        let size = p.get_part_size(self.room_for_units(&p));
+                        if !self.has_fuel_to_deliver(n, &p, &size, self.fuel_at(n, 0)) {
+                            low_fuel.push((n.clone(), Some(package.clone())));
+                        }
+                    }
+                }
                 // Start from the nearest critical node
                 for c in crit {
-                    let node = c.destination;
-                    // See whether there is any package on the train can be delivered to there
-                    for p in node.lock().unwrap().get_drop_off() {
-                        if self.package.contains_key(&p) {
-                            // let start_name = n.lock().unwrap().get_name();
-                            // return Some(
-                            //     c.path
-                            //         .get(&start_name)
-                            //         .unwrap()
-                            //         .as_ref()
-                            //         .unwrap()
-                            //         .to_string(),
-                            // );
-                            self.route = c.path.clone();
-                            return; // Early end the loop if found
+                    // This class of train cannot get there
+                    if c.distance_to_destination == i64::MAX {
+                        continue;
+                    }
+                    let node = c.destination.clone();
+                    // The number of packages which would be handled there
+                    let mut handled = 0;
+                    // See whether there is any package on the train can be delivered or handed over there
+                    let node_name = node.lock().unwrap().get_name();
+                    for p in self.get_unloadable_package() {
+                        if p.lock().unwrap().get_next_stop().lock().unwrap().get_name() == node_name
+                        {
+                            handled += 1;
                         }
                     }
                     // If no, see if there is any package can be picked up there
-                    for p in node.lock().unwrap().get_package() {
-                        let p_weight = p.lock().unwrap().get_weight();
-                        if !p.lock().unwrap().get_arrived() && self.load + p_weight <= self.capacity
-                        {
-                            // let start_name = n.lock().unwrap().get_name();
-                            // return Some(
-                            //     c.path
-                            //         .get(&start_name)
-                            //         .unwrap()
-                            //         .as_ref()
-                            //         .unwrap()
-                            //         .to_string(),
-                            // );
+                    let packages = node.lock().unwrap().get_package();
+                    for package in packages {
+                        let p = package.lock().unwrap();
+                        if self.available && !p.is_delivered() && self.can_deliver_from(&node, &p) {
+                            let fuel = self.fuel_at(&node, c.distance_to_destination);
+                            let size = p.get_part_size(self.room_for_units(&p));
+                            if !self.can_deliver_within_shift(&node, &p) {
+                                self.shift_limited = true;
+                            } else if !self.has_fuel_to_deliver(&node, &p, &size, fuel) {
+                                low_fuel.push((node.clone(), Some(package.clone())));
+                            } else {
+                                handled += 1;
+                            }
+                        }
+                    }
+                    if handled == 0 {
+                        continue;
+                    }
+                    // Do not start a trip the crew cannot finish within the shift
+                    if !self.fits_in_shift(n, &c, handled) {
+                        self.shift_limited = true;
+                        continue;
+                    }
+                    // Do not start a trip without the fuel to get to a refuelling station afterwards
+                    if !self.has_fuel_for(&c) {
+                        low_fuel.push((node.clone(), None));
+                        continue;
+                    }
+                    self.route = c.path.clone();
+                    self.target = Some(c.destination.clone());
+                    return; // Early end the loop if found
+                }
+                // If some work was skipped for the lack of fuel, refuel first at the nearest station
+                // from which a full tank is enough to do it, otherwise the work is left undone
+                let paths = n.lock().unwrap().get_shortest_path(&self.class);
+                for c in paths {
+                    if c.destination.lock().unwrap().get_refuel_time().is_some()
+                        && c.distance_to_destination != i64::MAX
+                        && self.fuel_needed(c.distance_to_destination) <= self.fuel
+                        && low_fuel
+                            .iter()
+                            .any(|(node, p)| self.can_work_after_refuel(&c.destination, node, p))
+                    {
+                        self.route = c.path.clone();
+                        self.target = Some(c.destination.clone());
+                        return;
+                    }
+                }
+                // If there is nothing left to do, head back to the depot
+                if let Some(home) = &self.home {
+                    let home_name = home.lock().unwrap().get_name();
+                    for c in n.lock().unwrap().get_shortest_path(&self.class) {
+                        if c.destination.lock().unwrap().get_name() == home_name {
                             self.route = c.path.clone();
-                            // self.load += p_weight;
-                            return; // Early end the loop if found
+                            self.target = Some(c.destination.clone());
+                            return;
                         }
                     }
                 }
@@ -135,89 +642,269 @@ impl Train {
         }
     }
 
-    /// Function to move the train
+    /// Function to describe the state of the train during the simulation as fields of a snapshot
+    /// The history and the broken rules of the train are saved separately
+    pub fn save_state(&self) -> String {
+        let (location, on_edge) = match &self.location {
+            Location::Node(n) => (n.lock().unwrap().get_name(), false),
+            Location::Edge(e) => (e.lock().unwrap().get_name(), true),
+        };
+        format!(
+            "location={},on_edge={},time={},trip_delay={},dwell={},departing={},end_trip={},replan={},available={},target={},load={},package={},duty={},since_rest={},rest={},shift_limited={},fuel={},delay={}",
+            location,
+            on_edge,
+            self.time,
+            self.trip_delay,
+            self.dwell,
+            self.departing,
+            self.end_trip,
+            self.replan,
+            self.available,
+            self.target
+                .as_ref()
+                .map_or("".to_string(), |t| t.lock().unwrap().get_name()),
+            self.load,
+            self.stack.join("|"),
+            self.duty,
+            self.since_rest,
+            self.rest,
+            self.shift_limited,
+            self.fuel,
+            self.delay
+        )
+    }
+
+    /// Function to continue from the state saved by `save_state`
+    /// The location, route, packages, history and broken rules are set separately
+    /// Returns the reason the state is not valid, naming the field
+    pub fn restore_state(&mut self, options: &HashMap<String, String>) -> Result<(), String> {
+        self.time = parse_field(options, "time")?;
+        self.trip_delay = parse_field(options, "trip_delay")?;
+        self.dwell = parse_field(options, "dwell")?;
+        self.departing = parse_field(options, "departing")?;
+        self.end_trip = parse_field(options, "end_trip")?;
+        self.replan = parse_field(options, "replan")?;
+        self.available = parse_field(options, "available")?;
+        self.load = parse_field(options, "load")?;
+        self.duty = parse_field(options, "duty")?;
+        self.since_rest = parse_field(options, "since_rest")?;
+        self.rest = parse_field(options, "rest")?;
+        self.shift_limited = parse_field(options, "shift_limited")?;
+        self.fuel = parse_field(options, "fuel")?;
+        self.delay = parse_field(options, "delay")?;
+        Ok(())
+    }
+
+    pub fn set_location(&mut self, location: Location) {
+        self.location = location;
+    }
+
+    /// Function to head to a target along a path found by Dijkstra's Algorithm from the target
+    pub fn set_route(
+        &mut self,
+        target: Option<Arc<Mutex<Node>>>,
+        route: HashMap<String, Option<String>>,
+    ) {
+        self.target = target;
+        self.route = route;
+    }
+
+    /// Function to put packages on the train, in the order they were loaded
+    pub fn set_package(&mut self, package: Vec<Arc<Mutex<Package>>>) {
+        self.stack = vec![];
+        self.package = HashMap::new();
+        for p in package {
+            let name = p.lock().unwrap().get_name();
+            self.stack.push(name.clone());
+            self.package.insert(name, p);
+        }
+    }
+
+    pub fn set_history(&mut self, history: Vec<History>) {
+        self.history = history;
+    }
+
+    pub fn set_violation(&mut self, violation: Vec<String>) {
+        self.violation = violation;
+    }
+
+    /// Function to get the packages on the train in the order they were loaded
+    pub fn get_package(&self) -> Vec<Arc<Mutex<Package>>> {
+        self.stack.iter().map(|n| self.package[n].clone()).collect()
+    }
+
+    /// Function to get the names of the packages on the train in the order they were loaded
+    pub fn get_package_names(&self) -> Vec<String> {
+        self.stack.clone()
+    }
+
+    /// Function to get the time the train has travelled on its current edge
+    pub fn get_time_on_edge(&self) -> u64 {
+        self.time
+    }
+
+    /// Function to get the time the trip on the current edge takes, including its random delay
+    pub fn get_trip_time(&self) -> u64 {
+        match &self.location {
+            Location::Edge(e) => e.lock().unwrap().get_travel_time(&self.class) + self.trip_delay,
+            Location::Node(_) => 0,
+        }
+    }
+
+    /// Function to get the node the train is at, or the node at the end of its edge
+    pub fn get_next_node(&self) -> Arc<Mutex<Node>> {
+        match &self.location {
+            Location::Node(n) => n.clone(),
+            Location::Edge(e) => e.lock().unwrap().get_end_node(),
+        }
+    }
+
+    /// Function to get the nodes the train will pass on the way to its target, ending with the target
+    pub fn get_route_path(&self) -> Vec<String> {
+        let mut ret = vec![];
+        let mut curr = self.get_next_node().lock().unwrap().get_name();
+        if let Location::Edge(_) = &self.location {
+            ret.push(curr.clone());
+        }
+        // The route cannot be longer than the number of nodes it knows
+        while let Some(Some(next)) = self.route.get(&curr) {
+            if ret.len() > self.route.len() {
+                break;
+            }
+            ret.push(next.clone());
+            curr = next.clone();
+        }
+        ret
+    }
+
+    pub fn get_target(&self) -> Option<Arc<Mutex<Node>>> {
+        self.target.clone()
+    }
+
+    pub fn get_delay(&self) -> i64 {
+        self.delay
+    }
+
+    /// Function to find the travel time along the route from the next node of the train to its target
+    /// The time the train may wait for closed lines on the way is not included
+    pub fn get_route_time(&self) -> i64 {
+        let mut node = self.get_next_node();
+        let mut ret = 0;
+        // The route of a train on an edge starts with the end of the edge
+        let skip = match &self.location {
+            Location::Node(_) => 0,
+            Location::Edge(_) => 1,
+        };
+        for next in self.get_route_path().into_iter().skip(skip) {
+            let edge = node.lock().unwrap().get_edge(next);
+            ret += edge.lock().unwrap().get_travel_time(&self.class) as i64;
+            node = edge.lock().unwrap().get_end_node();
+        }
+        ret
+    }
+
+    /// Function to follow a new path to the same target after a disruption
+    /// `before` is the travel time along the old route before the disruption and `after` the travel time
+    /// to the target from every node after it
+    /// The difference in travel time is counted as delay, the time waiting for a closed line is counted as it passes
+    pub fn reroute(
+        &mut self,
+        before: i64,
+        after: &HashMap<String, i64>,
+        path: HashMap<String, Option<String>>,
+    ) {
+        // A train on an edge can only change its path from the end of the edge
+        let from = self.get_next_node().lock().unwrap().get_name();
+        if after[&from] == i64::MAX {
+            return;
+        }
+        self.route = path;
+        self.delay += self.get_route_time() - before;
+    }
+
+    /// Function to move the train for 1 unit of time
+    /// Keeps track of the working time of the crew, which rests at a station when required
     pub fn deliver(&mut self, time: u64) {
+        self.blocked = None;
+        self.handed_over = false;
+        if self.rest == 0 {
+            self.move_train(time);
+            if self.end_trip {
+                return;
+            }
+        }
+        if self.rest == 0 {
+            self.since_rest += 1;
+        } else {
+            // Resting is not working time but it is part of the shift
+            self.rest -= 1;
+            if self.rest == 0 {
+                self.since_rest = 0;
+            }
+        }
+        self.duty += 1;
+        if let Some(shift) = self.shift {
+            if self.duty == shift + 1 {
+                self.violation
+                    .push(format!("exceeded its shift of {} at time {}", shift, time));
+            }
+        }
+    }
+
+    /// Function to move the train
+    fn move_train(&mut self, time: u64) {
         let current_node = match self.location.clone() {
             // If it is on an edge, just increment its travel time
             // If it is reaching a node, change its location to the node
             // and call this function again to drop/pick up package
             Location::Edge(edge) => {
-                if self.time < edge.lock().unwrap().get_journey_time() as u64 {
+                if self.time < edge.lock().unwrap().get_travel_time(&self.class) + self.trip_delay {
                     self.time += 1;
+                    self.burn_fuel(&edge, time);
                     return;
                 }
-                self.time = 0;
                 let node = edge.lock().unwrap().get_end_node();
+                // Queue on the edge if every platform of the station is taken
+                if !node.lock().unwrap().has_free_platform() {
+                    self.blocked = Some(node.lock().unwrap().get_train_on_platform());
+                    let len = self.history.len();
+                    self.history[len - 1].add_queue(1);
+                    return;
+                }
+                self.time = 0;
+                edge.lock().unwrap().leave(&self.name);
+                // The platform is taken for this unit of time even if the train leaves again at once
+                node.lock().unwrap().visit_train(self.name.clone());
                 self.location = Location::Node(node);
-                self.deliver(time);
+                self.move_train(time);
                 return;
             }
             // If the it is on a node
             Location::Node(current_node) => {
-                // Create a new history as described in the assignment
-                self.history.push(History::new(time, self.name.clone()));
-                let len = self.history.len();
-                // Get all the packages to be unloaded
-                let mut package_to_be_unload: Vec<Arc<Mutex<Package>>> = vec![];
-                let cur_node_name = current_node.lock().unwrap().get_name();
-                let reach_critical_node = current_node.lock().unwrap().is_critical();
-                // For all package on the train
-                for package in self.package.values() {
-                    let end_node_name = package
-                        .lock()
-                        .unwrap()
-                        .get_end_node()
-                        .lock()
-                        .unwrap()
-                        .get_name();
-                    // Compare the destination of the package with current node
-                    if cur_node_name == end_node_name {
-                        package_to_be_unload.push(package.clone());
+                if !self.departing {
+                    if self.dwell == 0 {
+                        // The train has just arrived, drop and pick up packages
+                        self.load_and_unload(time, current_node.clone());
+                    } else {
+                        // The train is still busy handling packages at this station
+                        self.dwell -= 1;
                     }
-                }
-                // Get all the name of the packages to be unloaded
-                let mut drop_package_name = vec![];
-                for p in package_to_be_unload {
-                    let pkg_name = p.lock().unwrap().get_name();
-                    // Unload the package from the train
-                    self.package.remove(&pkg_name).unwrap();
-                    // Decrement the load
-                    self.load -= p.lock().unwrap().get_weight();
-                    // Add this package to current node
-                    current_node.lock().unwrap().add_pick_up_package(p.clone());
-                    // Mark this package as delivered
-                    p.lock().unwrap().arrive();
-                    drop_package_name.push(pkg_name);
-                }
-                // Update this drop off node to the last history record
-                if len >= 2 {
-                    self.history[len - 2]
-                        .register_arrival(cur_node_name.clone(), drop_package_name);
-                }
-                // Get all the packages on this node
-                let packages = current_node.lock().unwrap().get_package();
-                let mut new_package_name = vec![];
-                for p in packages {
-                    let weight = p.lock().unwrap().get_weight();
-                    // Check whether this package has been delivered and the train can hold the package
-                    if self.load + weight <= self.capacity && !p.lock().unwrap().get_arrived() {
-                        // Load the package to the train
-                        self.package.insert(p.lock().unwrap().get_name(), p.clone());
-                        current_node
+                    if self.dwell > 0 {
+                        return;
+                    }
+                    self.departing = true;
+                    let replan = std::mem::take(&mut self.replan)
+                        && !current_node
                             .lock()
                             .unwrap()
-                            .remove_package(p.lock().unwrap().get_name());
-                        // Increment the load
-                        self.load += weight;
-                        new_package_name.push(p.lock().unwrap().get_name());
+                            .get_shortest_path(&self.class)
+                            .is_empty();
+                    if current_node.lock().unwrap().is_critical() || replan {
+                        // Find the nearest valid critical node from here if current node is a critical node
+                        // or the work has changed since the train chose its target
+                        self.find_new_target();
                     }
                 }
-                // Update the current history record
-                self.history[len - 1].register_departure(cur_node_name, new_package_name);
-                if reach_critical_node {
-                    // Find the nearest valid critical node from here if current node is a critical node
-                    self.find_new_target();
-                }
                 current_node.clone()
             }
         };
@@ -234,9 +921,47 @@ impl Train {
             //     node.as_ref().unwrap(),
             //     ""
             // );
+            let edge = current_node.lock().unwrap().get_edge(node.to_string());
+            let len = self.history.len();
+            // Rest here first if the crew would work too long without a break on the next edge
+            if let Some((after, duration)) = self.rest_rule {
+                let journey_time = edge.lock().unwrap().get_travel_time(&self.class);
+                if self.since_rest > 0 && self.since_rest + journey_time > after {
+                    self.rest = duration;
+                    self.history[len - 1].add_rest(duration);
+                    return;
+                }
+                if journey_time > after {
+                    self.violation.push(format!(
+                        "worked more than {} without rest on edge {} at time {}",
+                        after,
+                        edge.lock().unwrap().get_name(),
+                        time
+                    ));
+                }
+            }
+            // Wait at the node until the line opens again
+            if edge.lock().unwrap().is_closed() {
+                self.delay += 1;
+                self.history[len - 1].add_delay(1);
+                return;
+            }
+            // Wait at the node if the track is fully occupied
+            if !edge.lock().unwrap().has_room() {
+                self.blocked = Some(edge.lock().unwrap().get_train());
+                self.history[len - 1].add_wait(1);
+                return;
+            }
+            edge.lock().unwrap().enter(self.name.clone());
+            self.trip_delay = edge.lock().unwrap().draw_delay();
+            self.departing = false;
             self.time += 1;
-            self.location = Location::Edge(current_node.lock().unwrap().get_edge(node.to_string()));
+            self.burn_fuel(&edge, time);
+            self.location = Location::Edge(edge);
+            // The train leaves only after all the packages are handled
+            self.history[len - 1].set_departure_time(time);
         } else {
+            self.departing = false;
             // If there is no node to go, delete the current history entry because it has no destination
             self.history.remove(self.history.len() - 1);
             // Mark the train as end of trip
@@ -244,6 +969,161 @@ impl Train {
         }
     }
 
+    /// Function to check whether the train left a package at a hub for another train in the last call of `deliver`
+    pub fn has_handed_over(&self) -> bool {
+        self.handed_over
+    }
+
+    /// Function to let a train which has ended its trip look for work again
+    pub fn wake_up(&mut self) {
+        self.end_trip = false;
+    }
+
+    /// Function to let the train choose its target again at the next node it reaches
+    pub fn set_replan(&mut self) {
+        self.replan = true;
+    }
+
+    pub fn is_replanning(&self) -> bool {
+        self.replan
+    }
+
+    /// Function to get the trains holding the resource the train waited for in the last call of `deliver`,
+    /// if it could not move because of them
+    pub fn get_blocked_by(&self) -> Option<Vec<String>> {
+        self.blocked.clone()
+    }
+
+    /// Function to drop off and pick up the packages once the train arrives at a node
+    /// The time needed by the station to handle the packages is kept in `dwell`
+    fn load_and_unload(&mut self, time: u64, current_node: Arc<Mutex<Node>>) {
+        // Create a new history as described in the assignment
+        self.history.push(History::new(time, self.name.clone()));
+        let len = self.history.len();
+        // Get all the packages to be unloaded
+        let mut package_to_be_unload: Vec<Arc<Mutex<Package>>> = vec![];
+        let cur_node_name = current_node.lock().unwrap().get_name();
+        // For all package on the train, from the top of the stack
+        for name in self.stack.iter().rev() {
+            let package = &self.package[name];
+            let next_stop_name = package
+                .lock()
+                .unwrap()
+                .get_next_stop()
+                .lock()
+                .unwrap()
+                .get_name();
+            // Compare the next stop of the package with current node
+            if cur_node_name == next_stop_name {
+                package_to_be_unload.push(package.clone());
+            } else if self.lifo {
+                // Only the package on the top of the stack can be unloaded
+                break;
+            }
+        }
+        // Get all the name of the packages to be unloaded
+        let mut drop_package_name = vec![];
+        let mut transfer_package_name = vec![];
+        for p in package_to_be_unload {
+            let pkg_name = p.lock().unwrap().get_name();
+            // Unload the package from the train
+            self.package.remove(&pkg_name).unwrap();
+            self.stack.retain(|x| *x != pkg_name);
+            // Decrement the load
+            self.load.sub(&p.lock().unwrap().get_size());
+            // Add this package to current node
+            current_node.lock().unwrap().add_pick_up_package(p.clone());
+            if p.lock().unwrap().is_last_leg() {
+                // Mark this package as delivered
+                p.lock().unwrap().arrive(time);
+            } else {
+                // Leave this package at the hub for another train
+                p.lock().unwrap().transfer();
+                transfer_package_name.push(pkg_name.clone());
+            }
+            drop_package_name.push(pkg_name);
+        }
+        let dropped = drop_package_name.len();
+        // Update this drop off node to the last history record
+        // unless it is already done, as for a train which looks for work again after ending its trip
+        if len >= 2 && !self.history[len - 2].has_arrived() {
+            self.history[len - 2].register_arrival(
+                cur_node_name.clone(),
+                drop_package_name.clone(),
+                time,
+            );
+            self.handed_over = !transfer_package_name.is_empty();
+            self.history[len - 2].register_transfer(transfer_package_name);
+        }
+        // Get all the packages on this node
+        let packages = current_node.lock().unwrap().get_package();
+        let mut new_package_name = vec![];
+        for p in packages {
+            let mut package = p.lock().unwrap();
+            // Check whether this package has been delivered and the train is allowed to, can hold and can deliver the package
+            // A package the train has just handed over is left for another train
+            if self.available
+                && self.can_deliver_from(&current_node, &package)
+                && !package.is_delivered()
+                && !drop_package_name.contains(&package.get_name())
+            {
+                // Leave a package the crew cannot take to its next stop within the shift for another train
+                if !self.can_deliver_within_shift(&current_node, &package) {
+                    self.shift_limited = true;
+                    continue;
+                }
+                let units = self.room_for_units(&package);
+                // Leave a package the train would not have the fuel to take to its next stop
+                let fuel = self.fuel_at(&current_node, 0);
+                if !self.has_fuel_to_deliver(
+                    &current_node,
+                    &package,
+                    &package.get_part_size(units),
+                    fuel,
+                ) {
+                    continue;
+                }
+                if units < package.get_quantity() {
+                    // Only a part of the package fits, load it and leave the rest for another train
+                    let part = package.split(units, p.clone());
+                    self.load.add(&part.get_size());
+                    new_package_name.push(part.get_name());
+                    self.stack.push(part.get_name());
+                    self.package
+                        .insert(part.get_name(), Arc::new(Mutex::new(part)));
+                    continue;
+                }
+                // Load the package to the train
+                self.package.insert(package.get_name(), p.clone());
+                self.stack.push(package.get_name());
+                current_node
+                    .lock()
+                    .unwrap()
+                    .remove_package(package.get_name());
+                // Increment the load
+                self.load.add(&package.get_size());
+                new_package_name.push(package.get_name());
+            }
+        }
+        let handled = dropped + new_package_name.len();
+        // Update the current history record
+        self.history[len - 1].register_departure(
+            cur_node_name,
+            new_package_name,
+            self.load.clone(),
+        );
+        self.dwell = current_node.lock().unwrap().get_service_time(handled);
+        // Fill up the tank at a refuelling station
+        let refuel_time = current_node.lock().unwrap().get_refuel_time();
+        if let (Some(fuel_capacity), Some(refuel_time)) = (self.fuel_capacity, refuel_time) {
+            if self.fuel < fuel_capacity {
+                self.fuel = fuel_capacity;
+                self.dwell += refuel_time;
+                self.history[len - 1].register_refuel();
+            }
+        }
+    }
+
     pub fn get_history(&self) -> Vec<History> {
         self.history.clone()
     }
@@ -262,11 +1142,27 @@ pub struct History {
     p1: Vec<String>,
     n2: String,
     p2: Vec<String>,
+    /// Time at which the train reaches n2
+    arrival: u64,
+    /// Time the train waited at n1 for a free track before departing
+    wait: u64,
+    /// Time the train queued on the edge for a free platform at n2
+    queue: u64,
+    /// Time the crew rested at n1 before departing
+    rest: u64,
+    /// Time the train waited at n1 for a closed line to open
+    delay: u64,
+    /// Whether the train refuelled at n1
+    refuel: bool,
+    /// Packages of p2 which are handed over at n2 to another train rather than delivered
+    transfer: Vec<String>,
+    /// Load of the train when it departs from n1
+    load: Dimensions,
 }
 
 impl PartialEq for History {
     fn eq(&self, other: &Self) -> bool {
-        self.w == other.w
+        self.w == other.w && self.t == other.t
     }
 }
 
@@ -274,13 +1170,14 @@ impl Eq for History {}
 
 impl PartialOrd for History {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for History {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.w.cmp(&other.w)
+        // Records at the same time are ordered by train
+        self.w.cmp(&other.w).then_with(|| self.t.cmp(&other.t))
     }
 }
 
@@ -289,7 +1186,26 @@ impl Display for History {
         f.write_str(&format!(
             "W={}, T={}, N1={}, P1={:?}, N2={}, P2={:?}",
             self.w, self.t, self.n1, self.p1, self.n2, self.p2
-        ))
+        ))?;
+        if self.wait > 0 {
+            f.write_str(&format!(", WAIT={}", self.wait))?;
+        }
+        if self.queue > 0 {
+            f.write_str(&format!(", QUEUE={}", self.queue))?;
+        }
+        if self.rest > 0 {
+            f.write_str(&format!(", REST={}", self.rest))?;
+        }
+        if self.delay > 0 {
+            f.write_str(&format!(", DELAY={}", self.delay))?;
+        }
+        if self.refuel {
+            f.write_str(", REFUEL")?;
+        }
+        if !self.transfer.is_empty() {
+            f.write_str(&format!(", TRANSFER={:?}", self.transfer))?;
+        }
+        Ok(())
     }
 }
 
@@ -302,16 +1218,180 @@ impl History {
             p1: vec![],
             n2: "".to_string(),
             p2: vec![],
+            arrival: 0,
+            wait: 0,
+            queue: 0,
+            rest: 0,
+            delay: 0,
+            refuel: false,
+            transfer: vec![],
+            load: Dimensions::new(),
         }
     }
 
-    pub fn register_departure(&mut self, n1: String, p1: Vec<String>) {
+    pub fn register_departure(&mut self, n1: String, p1: Vec<String>, load: Dimensions) {
         self.n1 = n1;
         self.p1 = p1;
+        self.load = load;
     }
 
-    pub fn register_arrival(&mut self, n2: String, p2: Vec<String>) {
+    pub fn register_arrival(&mut self, n2: String, p2: Vec<String>, arrival: u64) {
         self.n2 = n2;
         self.p2 = p2;
+        self.arrival = arrival;
+    }
+
+    pub fn add_wait(&mut self, wait: u64) {
+        self.wait += wait;
+    }
+
+    pub fn add_queue(&mut self, queue: u64) {
+        self.queue += queue;
+    }
+
+    pub fn add_rest(&mut self, rest: u64) {
+        self.rest += rest;
+    }
+
+    pub fn add_delay(&mut self, delay: u64) {
+        self.delay += delay;
+    }
+
+    pub fn get_wait(&self) -> u64 {
+        self.wait
+    }
+
+    pub fn get_queue(&self) -> u64 {
+        self.queue
+    }
+
+    pub fn get_rest(&self) -> u64 {
+        self.rest
+    }
+
+    pub fn get_delay(&self) -> u64 {
+        self.delay
+    }
+
+    pub fn get_departure(&self) -> u64 {
+        self.w
+    }
+
+    pub fn get_arrival(&self) -> u64 {
+        self.arrival
+    }
+
+    pub fn get_load(&self) -> Dimensions {
+        self.load.clone()
+    }
+
+    /// Function to get the name of the node the train departs from
+    pub fn get_from(&self) -> String {
+        self.n1.clone()
+    }
+
+    /// Function to get the name of the node the train arrives at
+    pub fn get_to(&self) -> String {
+        self.n2.clone()
+    }
+
+    pub fn get_train(&self) -> String {
+        self.t.clone()
+    }
+
+    pub fn get_picked_up(&self) -> Vec<String> {
+        self.p1.clone()
+    }
+
+    pub fn get_dropped_off(&self) -> Vec<String> {
+        self.p2.clone()
+    }
+
+    pub fn has_arrived(&self) -> bool {
+        !self.n2.is_empty()
+    }
+
+    pub fn register_refuel(&mut self) {
+        self.refuel = true;
+    }
+
+    pub fn register_transfer(&mut self, transfer: Vec<String>) {
+        self.transfer = transfer;
+    }
+
+    /// Function to delay the departure until the packages are handled at n1
+    pub fn set_departure_time(&mut self, w: u64) {
+        self.w = w;
+    }
+
+    /// Function to describe the history record as a JSON object, the times waited are only given if any
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("w", self.w.into()),
+            ("t", self.t.clone().into()),
+            ("n1", self.n1.clone().into()),
+            ("p1", self.p1.clone().into()),
+            ("n2", self.n2.clone().into()),
+            ("p2", self.p2.clone().into()),
+            ("arrival", self.arrival.into()),
+        ];
+        for (key, time) in [
+            ("wait", self.wait),
+            ("queue", self.queue),
+            ("rest", self.rest),
+            ("delay", self.delay),
+        ] {
+            if time > 0 {
+                fields.push((key, time.into()));
+            }
+        }
+        if self.refuel {
+            fields.push(("refuel", true.into()));
+        }
+        if !self.transfer.is_empty() {
+            fields.push(("transfer", self.transfer.clone().into()));
+        }
+        Json::object(fields)
+    }
+
+    /// Function to describe the history record as fields of a snapshot
+    pub fn save_state(&self) -> String {
+        format!(
+            "w={},n1={},p1={},n2={},p2={},arrival={},wait={},queue={},rest={},delay={},refuel={},transfer={},load={}",
+            self.w,
+            self.n1,
+            self.p1.join("|"),
+            self.n2,
+            self.p2.join("|"),
+            self.arrival,
+            self.wait,
+            self.queue,
+            self.rest,
+            self.delay,
+            self.refuel,
+            self.transfer.join("|"),
+            self.load
+        )
+    }
+
+    /// Function to create the history record saved by `save_state`
+    /// Returns the reason the record is not valid, naming the field
+    pub fn restore_state(t: String, options: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Self {
+            w: parse_field(options, "w")?,
+            t,
+            n1: parse_field(options, "n1")?,
+            p1: parse_sequence(options.get("p1")),
+            n2: parse_field(options, "n2")?,
+            p2: parse_sequence(options.get("p2")),
+            arrival: parse_field(options, "arrival")?,
+            wait: parse_field(options, "wait")?,
+            queue: parse_field(options, "queue")?,
+            rest: parse_field(options, "rest")?,
+            delay: parse_field(options, "delay")?,
+            refuel: parse_field(options, "refuel")?,
+            transfer: parse_sequence(options.get("transfer")),
+            load: parse_field(options, "load")?,
+        })
     }
 }